@@ -7,6 +7,10 @@ use std::{env, fs};
 use std::fs::File;
 use std::path::Path;
 
+#[allow(dead_code)]
+#[path = "src/graphics/preprocessor.rs"]
+mod preprocessor;
+
 fn generate_opengl_bindings() {
 
     let out_dir = env::var("OUT_DIR").unwrap();
@@ -21,6 +25,28 @@ fn generate_opengl_bindings() {
 
 }
 
+fn generate_egl_bindings() {
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    // generate egl bindings (used for headless contexts), the functions are
+    // loaded at runtime so that libEGL is not needed to link
+
+    let mut file = File::create(&Path::new(&out_dir).join("egl_bindings.rs")).unwrap();
+
+    Registry::new(Api::Egl, (1, 5), Profile::Core, Fallbacks::All, [
+        "EGL_KHR_create_context",
+        "EGL_KHR_surfaceless_context",
+        "EGL_MESA_platform_surfaceless"
+    ])
+        .write_bindings(GlobalGenerator, &mut file)
+        .unwrap();
+
+}
+
+// vertex, tessellation control, tessellation evaluation, geometry, fragment and compute
+const SHADER_EXTENSIONS: [&str; 6] = ["vert", "tesc", "tese", "geom", "frag", "comp"];
+
 fn compile_shaders() {
     // compiling SPIR-V shaders
 
@@ -28,6 +54,8 @@ fn compile_shaders() {
 
     let gen_path = Path::new("shaders");
 
+    let out_dir = env::var("OUT_DIR").unwrap();
+
     let _ = std::fs::create_dir_all(gen_path);
 
     for entry in std::fs::read_dir("shaders").unwrap() {
@@ -51,7 +79,7 @@ fn compile_shaders() {
                 None => ""
             };
 
-            if ext == "vert" || ext == "frag" {
+            if SHADER_EXTENSIONS.contains(&ext) {
 
                 let out_file = format!(
                     "{}/{}.spv",
@@ -63,6 +91,19 @@ fn compile_shaders() {
 
                 let _ = fs::remove_file(&out_file);
 
+                // resolve #include, glslc compiles the preprocessed copy
+
+                let preprocessed = match preprocessor::preprocess(in_file, &[]) {
+                    Ok(preprocessed) => preprocessed,
+                    Err(e) => {
+                        eprintln!("{}: {}", e.path, e.error);
+                        std::process::exit(1);
+                    }
+                };
+
+                let preprocessed_path = Path::new(&out_dir).join(in_path.file_name().unwrap());
+                let preprocessed_file = preprocessed_path.to_str().unwrap_or_default();
+                fs::write(&preprocessed_path, &preprocessed.source).unwrap();
 
                 /*
                 let output = Command::new("glslangValidator")
@@ -78,20 +119,24 @@ fn compile_shaders() {
                     .arg("-mfmt=bin")
                     .arg("-o")
                     .arg(&out_file)
-                    .arg(&in_file)
+                    .arg(&preprocessed_file)
                     .output();
 
                 let output = match output {
                     Ok(output) => output,
                     Err(e) => {
-                        println!("failed to launch glslangValidator : {}", e);
+                        if compile_shader_in_process(in_file, &preprocessed, &out_file, ext) {
+                            continue;
+                        }
+                        println!("cargo:warning=failed to launch glslc ({}), shaders are compiled at runtime", e);
                         return;
                     }
                 };
 
                 if !output.status.success() {
+                    let log = preprocessed.map_log(&String::from_utf8_lossy(&output.stderr).replace(preprocessed_file, in_file));
                     io::stdout().write_all(&output.stdout).unwrap();
-                    io::stderr().write_all(&output.stderr).unwrap();
+                    io::stderr().write_all(log.as_bytes()).unwrap();
                     std::process::exit(output.status.code().unwrap_or_default());
                 }
 
@@ -102,12 +147,48 @@ fn compile_shaders() {
 
     println!("cargo:rerun-if-changed=shaders");
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/graphics/preprocessor.rs");
+
+}
+
+// fallback for missing glslc, same semantics as --target-env=opengl
+#[cfg(feature = "shaderc")]
+fn compile_shader_in_process(in_file: &str, preprocessed: &preprocessor::PreprocessedSource, out_file: &str, ext: &str) -> bool {
+
+    let kind = match ext {
+        "vert" => shaderc::ShaderKind::Vertex,
+        "tesc" => shaderc::ShaderKind::TessControl,
+        "tese" => shaderc::ShaderKind::TessEvaluation,
+        "geom" => shaderc::ShaderKind::Geometry,
+        "comp" => shaderc::ShaderKind::Compute,
+        _ => shaderc::ShaderKind::Fragment
+    };
+
+    let mut compiler = shaderc::Compiler::new().unwrap();
+    let mut options = shaderc::CompileOptions::new().unwrap();
+    options.set_target_env(shaderc::TargetEnv::OpenGL, shaderc::EnvVersion::OpenGL4_5 as u32);
+
+    match compiler.compile_into_spirv(&preprocessed.source, kind, in_file, "main", Some(&options)) {
+        Ok(artifact) => {
+            fs::write(out_file, artifact.as_binary_u8()).unwrap();
+            return true;
+        },
+        Err(e) => {
+            io::stderr().write_all(preprocessed.map_log(&e.to_string()).as_bytes()).unwrap();
+            std::process::exit(1);
+        }
+    }
+}
 
+#[cfg(not(feature = "shaderc"))]
+fn compile_shader_in_process(_in_file: &str, _preprocessed: &preprocessor::PreprocessedSource, _out_file: &str, _ext: &str) -> bool {
+    return false;
 }
 
 fn main() {
 
     generate_opengl_bindings();
+    generate_egl_bindings();
     compile_shaders();
 
 }
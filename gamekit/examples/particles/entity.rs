@@ -2,7 +2,7 @@
 // Entity
 //
 
-use crate::graphics::{sprite::{Sprite, SpriteData}, types::{Rectangle, Color, Vector2}, api::Api};
+use gamekit::graphics::{sprite::{Sprite, SpriteData}, types::{Rectangle, Color, Vector2}, api::Api};
 
 defaults!();
 
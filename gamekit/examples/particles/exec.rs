@@ -0,0 +1,290 @@
+//
+// Executor
+//
+
+defaults!();
+
+static BATCH_CAPACITY: usize = 100;
+static NUM_PARTICLES:usize = BATCH_CAPACITY;
+
+use gamekit::graphics::{
+    shader::{Shader, ShaderType},
+    program::Program,
+    buffers::{BufferUsage, ShaderStorageBufferObject, UniformBufferObject},
+    texture::Texture,
+    application,
+    api::{Api, BlendMode}, sprite_batch::{SpriteBatch},
+    postprocess::PostProcessChain,
+    layout::{BlockLayout, Layout},
+    error::GamekitError
+};
+
+use crate::entity::Entity;
+
+//const SHADER: &[u8] = include_bytes!("<shader_name>.spv");
+
+#[derive(BlockLayout)]
+struct ShaderData {
+    resolution_x: f32,
+    resolution_y: f32,
+    x_min: f32,
+    x_max: f32,
+    y_min: f32,
+    y_max: f32,
+    time: f32,
+    time_delta: f32,
+    frame: i32,
+}
+
+#[derive(BlockLayout)]
+struct UniformData {
+    a: f32,
+    b: f32
+}
+
+#[allow(dead_code)]
+pub struct MyExecutor {
+    program: Program,
+    texture: Texture,
+    shader_data: ShaderData,
+    shader_data_object: ShaderStorageBufferObject<ShaderData>,
+    uniform_data: UniformData,
+    uniform_data_object: UniformBufferObject<UniformData>,
+    sprite_batch: SpriteBatch,
+    post_process: PostProcessChain,
+    entities: Vec<Entity>
+}
+
+impl application::Executor for MyExecutor {
+
+    fn new(api: &mut dyn Api) -> Result<MyExecutor, GamekitError> {
+        info!("Executor new");
+
+        let metrics = api.get_metrics();
+
+        let texture = Texture::new("assets/particle.png")?;
+        let vertex_shader = Shader::new(ShaderType::VertexShader, "shaders/shader.vert.spv")?;
+        let fragment_shader = Shader::new(ShaderType::FragmentShader, "shaders/shader.frag.spv")?;
+        let program = Program::new(&vec!(vertex_shader, fragment_shader))?;
+
+        // the blocks declared in common.glsl, unused blocks are not reported
+        if let Some(block) = program.interface().storage_block("data_buffer") {
+            block.validate_layout::<ShaderData>(Layout::Std430)?;
+        }
+
+        if let Some(block) = program.interface().uniform_block("uniform_buffer") {
+            block.validate_layout::<UniformData>(Layout::Std140)?;
+        }
+
+        let shader_data = ShaderData {
+            resolution_x: metrics.width as f32,
+            resolution_y: metrics.height as f32,
+            x_min: 0.0f32,
+            x_max: metrics.width as f32,
+            y_min: 0.0f32,
+            y_max: metrics.height as f32,
+            time: metrics.time_seconds,
+            time_delta: metrics.delta_seconds,
+            frame: metrics.frame_counter as i32
+        };
+
+        let mut shader_data_object = ShaderStorageBufferObject::new(1, BufferUsage::DynamicDraw)?;
+        shader_data_object.copy_to_buffer(&shader_data);
+
+        let uniform_data = UniformData {
+            a: 1.11,
+            b: 2.22
+        };
+
+        let mut uniform_data_object = UniformBufferObject::new(2, BufferUsage::DynamicDraw)?;
+        uniform_data_object.copy_to_buffer(&uniform_data);
+
+        let sprite_batch = SpriteBatch::new(&texture, BATCH_CAPACITY)?;
+
+        let mut post_process = PostProcessChain::new(metrics.width, metrics.height)?;
+        post_process.add_bloom(0.5, 1.2, 2.0)?;
+
+        //let entities = vec![Entity::new(); sprite_batch.capacity()];
+        let entities = vec![Entity::new(); NUM_PARTICLES];
+
+        let executor = MyExecutor {
+            program,
+            texture,
+            shader_data,
+            shader_data_object,
+            uniform_data,
+            uniform_data_object,
+            sprite_batch,
+            post_process,
+            entities
+        };
+
+        return Ok(executor);
+    }
+
+    fn initialize(&mut self, api: &mut dyn Api) {
+        info!("Executor initialize");
+
+        for entity in &mut self.entities {
+            entity.initialize(api);
+        }
+
+    }
+
+    fn free(&mut self, _api: &mut dyn Api) {
+        info!("Executor free");
+    }
+
+    fn update_layout(&mut self, api: &mut dyn Api) {
+        info!("Executor update layout");
+
+        let metrics = api.get_metrics();
+        if let Err(err) = self.post_process.resize(metrics.width, metrics.height) {
+            error!("failed to resize post processing: {}", err);
+        }
+    }
+
+    fn update_state(&mut self, api: &mut dyn Api, delta: f32) {
+        //info!("Executor update state");
+
+        self.sprite_batch.begin();
+
+        for entity in &mut self.entities {
+            entity.update(api, delta);
+            self.sprite_batch.push(entity);
+        }
+
+        self.sprite_batch.end();
+
+    }
+
+    fn update_graphics(&mut self, api: &mut dyn Api) {
+        //info!("Executor update graphics");
+
+        self.post_process.begin(api);
+
+        self.program.use_program();
+        self.update_uniforms(api);
+        self.shader_data_object.unbind();
+
+        self.shader_data_object.bind();
+        self.uniform_data_object.bind();
+
+        api.set_blend_mode(BlendMode::Additive);
+        self.sprite_batch.draw(api);
+        api.set_blend_mode(BlendMode::Normal);
+
+        self.shader_data_object.unbind();
+
+        self.post_process.end(api);
+
+    }
+
+}
+
+impl MyExecutor {
+    fn update_uniforms(&mut self, api: &mut dyn Api) {
+
+        let metrics = api.get_metrics();
+
+        {
+            let data = &mut self.shader_data;
+
+            data.time = metrics.time_seconds;
+            data.time_delta = metrics.delta_seconds;
+            data.frame = metrics.frame_counter as i32;
+
+            self.shader_data_object.copy_to_buffer(data);
+        }
+
+        {
+            let data = &mut self.uniform_data;
+
+            data.a = metrics.time_seconds;
+            data.b = metrics.time_seconds + 1.0;
+            data.b = metrics.time_seconds + 2.0;
+
+            self.uniform_data_object.copy_to_buffer(data);
+        }
+
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use gamekit::graphics::{
+        golden::GoldenTest,
+        graphics::GraphicsMode,
+        headless::HeadlessContext,
+        application::{Application, Executor},
+        recording::{RecordingBackend, Command},
+        primitives::DrawMode,
+        api::BlendMode
+    };
+    use super::{MyExecutor, NUM_PARTICLES};
+
+    #[test]
+    fn particles_match_golden_image() {
+        let test = GoldenTest::new("particles", GraphicsMode::Software { width: 320, height: 240 });
+        if let Err(err) = test.run::<MyExecutor>() {
+            panic!("{}", err);
+        }
+    }
+
+    // the GL path (shaders, post processing, headless framebuffer) against the
+    // software reference. The software backend copies post process passes, so
+    // bloom is disabled. All particles start on top of each other and every
+    // additive blend rounds to 8 bits, rasterizers round differently and the
+    // errors add up, hence the larger tolerance.
+    #[test]
+    fn particles_headless_match_software_golden_image() {
+        if let Err(err) = HeadlessContext::is_available() {
+            eprintln!("skipping headless golden test: {}", err);
+            return;
+        }
+
+        let mut test = GoldenTest::new("particles_headless", GraphicsMode::Headless { width: 320, height: 240 });
+        test.reference_name = "particles".to_string();
+        test.tolerance = 40;
+
+        let result = test.run_with(|api| {
+            let mut executor = MyExecutor::new(api)?;
+            for pass in executor.post_process.passes_mut() {
+                pass.enabled = false;
+            }
+            return Ok(executor);
+        });
+
+        if let Err(err) = result {
+            panic!("{}", err);
+        }
+    }
+
+    #[test]
+    fn particles_draw_one_additive_batch() {
+        let backend = Rc::new(RecordingBackend::new());
+        let mut app = match Application::<MyExecutor>::with_backend(60, backend.clone(), 320, 240) {
+            Ok(app) => app,
+            Err(err) => panic!("{}", err)
+        };
+
+        backend.clear_commands();
+        assert!(app.run_frames(1, 1.0 / 60.0));
+
+        let commands = backend.take_commands();
+        let draws: Vec<usize> = commands.iter().enumerate()
+            .filter(|(_, command)| matches!(command, Command::DrawElements {..} | Command::DrawElementsInstanced {..}))
+            .map(|(index, _)| index)
+            .collect();
+
+        assert_eq!(draws.len(), 1);
+        assert_eq!(commands[draws[0]], Command::DrawElements { mode: DrawMode::Triangles, count: NUM_PARTICLES * 6 });
+
+        let blend_mode = commands[..draws[0]].iter().rev().find_map(|command| match command {
+            Command::SetBlendMode(blend_mode) => Some(*blend_mode),
+            _ => None
+        });
+        assert_eq!(blend_mode, Some(BlendMode::Additive));
+    }
+}
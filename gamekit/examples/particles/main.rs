@@ -0,0 +1,52 @@
+//
+// Particles
+//
+
+#[macro_use]
+extern crate gamekit;
+
+use log::LevelFilter;
+use simplelog::{Config, TermLogger, TerminalMode, ColorChoice};
+use gamekit::{application::{Application, ApplicationContext, Executor}};
+use crate::exec::MyExecutor;
+
+mod exec;
+mod entity;
+
+defaults!();
+
+const FRAMERATE: i32 = 120;
+
+fn main() {
+    let _ = TermLogger::init(
+        LevelFilter::Debug,
+        Config::default(),
+        TerminalMode::Mixed,
+        ColorChoice::Auto
+    );
+
+    info!("startup");
+
+    let _context = run::<MyExecutor>();
+
+    info!("shutdown");
+
+}
+
+fn run<Exec: Executor>() -> Option<ApplicationContext> {
+
+    let app = Application::<Exec>::new(FRAMERATE);
+
+    match app {
+        Ok(mut app) => {
+            let context = app.get_context();
+            app.run();
+            return Some(context);
+        },
+        Err(err) => {
+            error!("{}", err);
+            return None;
+        }
+    };
+
+}
@@ -0,0 +1,55 @@
+//
+// Shadertoy
+//
+
+use log::LevelFilter;
+use simplelog::{Config, TermLogger, TerminalMode, ColorChoice};
+use gamekit::{defaults, application::{Application, ApplicationContext}, graphics::{graphics::GraphicsMode, shadertoy::{ShadertoyConfig, ShadertoyExecutor}}};
+
+defaults!();
+
+const FRAMERATE: i32 = 120;
+
+// cargo run --example shadertoy -- [image] [--common <file>] [--buffer-a..d <file>] [--channel0..3 <png|A|B|C|D>]
+fn main() {
+    let _ = TermLogger::init(
+        LevelFilter::Debug,
+        Config::default(),
+        TerminalMode::Mixed,
+        ColorChoice::Auto
+    );
+
+    info!("startup");
+
+    let args: Vec<String> = std::iter::once("--shadertoy".to_string()).chain(std::env::args().skip(1)).collect();
+
+    let _context = match ShadertoyConfig::from_args(&args) {
+        Ok(Some(config)) => run(&config),
+        Ok(None) => None,
+        Err(err) => {
+            error!("{}", err);
+            None
+        }
+    };
+
+    info!("shutdown");
+
+}
+
+fn run(config: &ShadertoyConfig) -> Option<ApplicationContext> {
+
+    let app = Application::with_factory(FRAMERATE, GraphicsMode::Window, |api| ShadertoyExecutor::with_config(api, config));
+
+    match app {
+        Ok(mut app) => {
+            let context = app.get_context();
+            app.run();
+            return Some(context);
+        },
+        Err(err) => {
+            error!("{}", err);
+            return None;
+        }
+    };
+
+}
@@ -0,0 +1,20 @@
+//
+// Common Declarations
+//
+
+layout(std430, binding = 1) buffer data_buffer {
+    float resolution_x;
+    float resolution_y;
+    float x_min;
+    float x_max;
+    float y_min;
+    float y_max;
+    float time;
+    float time_delta;
+    int frame;
+} data;
+
+layout(std140, binding = 2) uniform uniform_buffer {
+    float a;
+    float b;
+} uniforms;
@@ -0,0 +1,17 @@
+//
+// Fullscreen Vertex Shader
+//
+
+#version 450 core
+
+// single triangle covering the viewport, drawn with 3 vertices and no buffers
+
+layout (location = 0) out vec2 oTextureCoord;
+
+void main() {
+    vec2 position = vec2(float((gl_VertexID & 1) << 2) - 1.0, float((gl_VertexID & 2) << 1) - 1.0);
+
+    oTextureCoord = position * 0.5 + 0.5;
+
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,38 @@
+//
+// Post Processing: Bloom Bright Pass
+//
+
+#version 450 core
+
+layout(std140, binding = 3) uniform pass_uniforms {
+    vec2 resolution;
+    vec2 texel_size;
+    float time;
+    int frame;
+    vec4 params[4];
+} pass;
+
+layout (binding = 0) uniform sampler2D iSource;
+layout (binding = 1) uniform sampler2D iScene;
+layout (binding = 2) uniform sampler2D iLookup;
+
+layout (location = 0) in vec2 iTextureCoord;
+
+layout (location = 0) out vec4 oColor;
+
+// params[0].x = threshold, params[0].y = soft knee
+
+void main() {
+    vec4 color = texture(iSource, iTextureCoord);
+
+    float threshold = pass.params[0].x;
+    float knee = max(pass.params[0].y, 0.0001);
+
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee);
+
+    float contribution = max(soft, brightness - threshold) / max(brightness, 0.0001);
+
+    oColor = vec4(color.rgb * contribution, color.a);
+}
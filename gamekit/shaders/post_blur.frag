@@ -0,0 +1,38 @@
+//
+// Post Processing: Gaussian Blur
+//
+
+#version 450 core
+
+layout(std140, binding = 3) uniform pass_uniforms {
+    vec2 resolution;
+    vec2 texel_size;
+    float time;
+    int frame;
+    vec4 params[4];
+} pass;
+
+layout (binding = 0) uniform sampler2D iSource;
+layout (binding = 1) uniform sampler2D iScene;
+layout (binding = 2) uniform sampler2D iLookup;
+
+layout (location = 0) in vec2 iTextureCoord;
+
+layout (location = 0) out vec4 oColor;
+
+// separable 9-tap gaussian, params[0].xy = direction, params[0].z = radius scale
+
+const float weights[5] = float[](0.2270270270, 0.1945945946, 0.1216216216, 0.0540540541, 0.0162162162);
+
+void main() {
+    vec2 step = pass.params[0].xy * pass.params[0].z * pass.texel_size;
+
+    vec4 color = texture(iSource, iTextureCoord) * weights[0];
+
+    for (int i = 1; i < 5; i++) {
+        color += texture(iSource, iTextureCoord + step * float(i)) * weights[i];
+        color += texture(iSource, iTextureCoord - step * float(i)) * weights[i];
+    }
+
+    oColor = color;
+}
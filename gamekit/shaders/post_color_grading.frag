@@ -0,0 +1,47 @@
+//
+// Post Processing: Color Grading
+//
+
+#version 450 core
+
+layout(std140, binding = 3) uniform pass_uniforms {
+    vec2 resolution;
+    vec2 texel_size;
+    float time;
+    int frame;
+    vec4 params[4];
+} pass;
+
+layout (binding = 0) uniform sampler2D iSource;
+layout (binding = 1) uniform sampler2D iScene;
+layout (binding = 2) uniform sampler2D iLookup;
+
+layout (location = 0) in vec2 iTextureCoord;
+
+layout (location = 0) out vec4 oColor;
+
+// iLookup is a 2D strip of size^2 x size texels (e.g. 256x16): blue selects the
+// slice from left to right, red runs along x and green along y inside a slice.
+// params[0].x = intensity, params[0].y = size
+
+vec3 lookup(vec3 color, float size) {
+    float slice = color.b * (size - 1.0);
+    float slice0 = floor(slice);
+    float slice1 = min(slice0 + 1.0, size - 1.0);
+
+    float x = (color.r * (size - 1.0) + 0.5) / (size * size);
+    float y = (color.g * (size - 1.0) + 0.5) / size;
+
+    vec3 color0 = textureLod(iLookup, vec2(x + slice0 / size, y), 0.0).rgb;
+    vec3 color1 = textureLod(iLookup, vec2(x + slice1 / size, y), 0.0).rgb;
+
+    return mix(color0, color1, slice - slice0);
+}
+
+void main() {
+    vec4 color = texture(iSource, iTextureCoord);
+
+    vec3 graded = lookup(clamp(color.rgb, 0.0, 1.0), pass.params[0].y);
+
+    oColor = vec4(mix(color.rgb, graded, pass.params[0].x), color.a);
+}
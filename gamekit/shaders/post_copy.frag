@@ -0,0 +1,25 @@
+//
+// Post Processing: Copy
+//
+
+#version 450 core
+
+layout(std140, binding = 3) uniform pass_uniforms {
+    vec2 resolution;
+    vec2 texel_size;
+    float time;
+    int frame;
+    vec4 params[4];
+} pass;
+
+layout (binding = 0) uniform sampler2D iSource;
+layout (binding = 1) uniform sampler2D iScene;
+layout (binding = 2) uniform sampler2D iLookup;
+
+layout (location = 0) in vec2 iTextureCoord;
+
+layout (location = 0) out vec4 oColor;
+
+void main() {
+    oColor = texture(iSource, iTextureCoord);
+}
@@ -0,0 +1,52 @@
+//
+// Post Processing: CRT
+//
+
+#version 450 core
+
+layout(std140, binding = 3) uniform pass_uniforms {
+    vec2 resolution;
+    vec2 texel_size;
+    float time;
+    int frame;
+    vec4 params[4];
+} pass;
+
+layout (binding = 0) uniform sampler2D iSource;
+layout (binding = 1) uniform sampler2D iScene;
+layout (binding = 2) uniform sampler2D iLookup;
+
+layout (location = 0) in vec2 iTextureCoord;
+
+layout (location = 0) out vec4 oColor;
+
+// params[0].x = curvature, params[0].y = scanline intensity,
+// params[0].z = scanline count (0 = every other pixel row), params[0].w = mask intensity
+
+vec2 curve(vec2 uv, float curvature) {
+    uv = uv * 2.0 - 1.0;
+    uv *= 1.0 + (uv.yx * uv.yx) * curvature;
+    return uv * 0.5 + 0.5;
+}
+
+void main() {
+    vec2 uv = curve(iTextureCoord, pass.params[0].x);
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        oColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec4 color = texture(iSource, uv);
+
+    float lines = pass.params[0].z > 0.0 ? pass.params[0].z : pass.resolution.y * 0.5;
+    float scanline = 0.5 + 0.5 * sin(uv.y * lines * 6.2831853);
+    color.rgb *= 1.0 - pass.params[0].y * (1.0 - scanline);
+
+    // aperture grille, one color channel per pixel column
+    int column = int(gl_FragCoord.x) % 3;
+    vec3 mask = vec3(column == 0 ? 1.0 : 0.0, column == 1 ? 1.0 : 0.0, column == 2 ? 1.0 : 0.0);
+    color.rgb *= mix(vec3(1.0), mask * 1.5 + 0.25, pass.params[0].w);
+
+    oColor = color;
+}
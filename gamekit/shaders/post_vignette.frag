@@ -0,0 +1,35 @@
+//
+// Post Processing: Vignette
+//
+
+#version 450 core
+
+layout(std140, binding = 3) uniform pass_uniforms {
+    vec2 resolution;
+    vec2 texel_size;
+    float time;
+    int frame;
+    vec4 params[4];
+} pass;
+
+layout (binding = 0) uniform sampler2D iSource;
+layout (binding = 1) uniform sampler2D iScene;
+layout (binding = 2) uniform sampler2D iLookup;
+
+layout (location = 0) in vec2 iTextureCoord;
+
+layout (location = 0) out vec4 oColor;
+
+// params[0].x = strength, params[0].y = radius, params[0].z = softness
+
+void main() {
+    vec4 color = texture(iSource, iTextureCoord);
+
+    float aspect = pass.resolution.x / max(pass.resolution.y, 1.0);
+    vec2 offset = (iTextureCoord - 0.5) * vec2(aspect, 1.0);
+
+    float radius = pass.params[0].y;
+    float vignette = smoothstep(radius, radius - pass.params[0].z, length(offset));
+
+    oColor = vec4(color.rgb * mix(1.0, vignette, pass.params[0].x), color.a);
+}
@@ -4,22 +4,7 @@
 
 #version 450 core
 
-layout(std140, binding = 1) buffer data_buffer {
-    float resolution_x;
-    float resolution_y;
-    float x_min;
-    float x_max;
-    float y_min;
-    float y_max;
-    float time;
-    float time_delta;
-    int frame;
-} data;
-
-layout(std140, binding = 2) uniform uniform_buffer {
-    float a;
-    float b;
-} uniforms;
+#include "common.glsl"
 
 layout (binding = 0) uniform sampler2D iTexture;
 
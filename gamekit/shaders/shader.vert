@@ -4,22 +4,7 @@
 
 #version 450 core
 
-layout(std140, binding = 1) buffer data_buffer {
-    float resolution_x;
-    float resolution_y;
-    float x_min;
-    float x_max;
-    float y_min;
-    float y_max;
-    float time;
-    float time_delta;
-    int frame;
-} data;
-
-layout(std140, binding = 2) uniform uniform_buffer {
-    float a;
-    float b;
-} uniforms;
+#include "common.glsl"
 
 layout (location = 0) in vec3 iPosition;
 layout (location = 1) in vec4 iColor;
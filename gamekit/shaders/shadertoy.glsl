@@ -0,0 +1,13 @@
+//
+// Shadertoy Image
+//
+
+// pulsing glow around the center, paste any Shadertoy image tab here
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = (fragCoord - 0.5 * iResolution.xy) / iResolution.y;
+    float d = length(uv);
+    float m = abs(sin(iTime)) * 0.02 / d;
+    vec3 col = vec3(m);
+    fragColor = vec4(col, 1.0);
+}
@@ -4,22 +4,39 @@
 
 extern crate sdl2;
 
-use crate::graphics::{graphics::{Graphics, Metrics}, primitives::{DrawMode, Primitives}};
-
-use super::gl;
+use crate::graphics::{graphics::{Graphics, Metrics, Mouse}, primitives::{DrawMode}, image::Image, render_target::RenderTarget, error::GamekitError};
 
 defaults!();
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BlendMode {
     Normal,
     Additive,
-    Multiply
+    Multiply,
+    Opaque
+}
+
+// Makes writes of compute shaders visible to the following commands, named by
+// how the written data is read afterwards (glMemoryBarrier)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MemoryBarrier {
+    VertexAttribArray,
+    ElementArray,
+    Uniform,
+    TextureFetch,
+    ShaderImageAccess,
+    Command,
+    BufferUpdate,
+    Framebuffer,
+    ShaderStorage,
+    All
 }
 
 pub trait Api {
     fn get_metrics(&self) -> &Metrics;
     fn get_time_seconds(&self) -> f32;
     fn get_delta_seconds(&self) -> f32;
+    fn get_mouse(&self) -> &Mouse;
     fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32);
     fn clear(&self);
     fn draw_arrays(&self, mode: DrawMode, ofs: usize, count: usize);
@@ -28,10 +45,13 @@ pub trait Api {
     fn get_random(&self) -> f32;
     fn get_random_range(&self, min: f32, max: f32) -> f32;
     fn set_blend_mode(&self, blend_mode: BlendMode);
+    fn memory_barrier(&self, barriers: &[MemoryBarrier]);
+    fn memory_barrier_all(&self);
+    fn set_render_target(&self, render_target: Option<&RenderTarget>);
+    fn read_framebuffer(&self) -> Result<Image, GamekitError>;
+    fn save_screenshot(&self, file_path: &str) -> Result<(), GamekitError>;
 }
 
-static mut RAND_SEED: i32 = 1;
-
 impl Api for Graphics {
 
     fn get_metrics(&self) -> &Metrics {
@@ -46,11 +66,14 @@ impl Api for Graphics {
         return self.metrics.delta_seconds;
     }
 
+    fn get_mouse(&self) -> &Mouse {
+        return &self.mouse;
+    }
+
     fn get_random(&self) -> f32 {
-        unsafe {
-            RAND_SEED = RAND_SEED.wrapping_mul(16807);
-            return ((RAND_SEED as f32) * 4.6566129e-010f32).abs();
-        }
+        let seed = self.random_seed.get().wrapping_mul(16807);
+        self.random_seed.set(seed);
+        return ((seed as f32) * 4.6566129e-010f32).abs();
     }
 
     fn get_random_range(&self, min: f32, max: f32) -> f32 {
@@ -60,48 +83,53 @@ impl Api for Graphics {
     }
 
     fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
-        Primitives::clear_color(red, green, blue, alpha);
+        self.backend().clear_color(red, green, blue, alpha);
     }
 
     fn clear(&self) {
-        Primitives::clear();
+        self.backend().clear();
     }
 
     fn draw_arrays(&self, mode: DrawMode, ofs: usize, count: usize) {
-        Primitives::draw_arrays(mode, ofs, count);
+        self.backend().draw_arrays(mode, ofs, count);
     }
 
     fn draw_elements(&self, mode: DrawMode, count: usize) {
-        Primitives::draw_elements(mode, count);
+        self.backend().draw_elements(mode, count);
     }
 
     fn draw_elements_instanced(&self, mode: DrawMode, count: usize, num_instances: usize) {
-        Primitives::draw_elements_instanced(mode, count, num_instances);
+        self.backend().draw_elements_instanced(mode, count, num_instances);
     }
 
     fn set_blend_mode(&self, blend_mode: BlendMode) {
+        self.backend().set_blend_mode(blend_mode);
+    }
 
-        let src;
-        let dst;
-
-        match blend_mode {
-            BlendMode::Normal => {
-                src = gl::SRC_ALPHA;
-                dst = gl::ONE_MINUS_SRC_ALPHA;
-            },
-            BlendMode::Additive => {
-                src = gl::SRC_ALPHA;
-                dst = gl::ONE;
-            },
-            BlendMode::Multiply => {
-                src = gl::DST_COLOR;
-                dst = gl::ZERO;
+    fn memory_barrier(&self, barriers: &[MemoryBarrier]) {
+        self.backend().memory_barrier(barriers);
+    }
+
+    fn memory_barrier_all(&self) {
+        self.backend().memory_barrier(&[MemoryBarrier::All]);
+    }
+
+    fn set_render_target(&self, render_target: Option<&RenderTarget>) {
+        match render_target {
+            Some(render_target) => render_target.bind(),
+            None => {
+                self.backend().bind_framebuffer(0);
+                self.backend().viewport(0, 0, self.metrics.width, self.metrics.height);
             }
         }
+    }
 
-        unsafe {
-            gl::BlendFunc(src, dst);
-        }
+    fn read_framebuffer(&self) -> Result<Image, GamekitError> {
+        return Graphics::read_framebuffer(self);
+    }
+
+    fn save_screenshot(&self, file_path: &str) -> Result<(), GamekitError> {
+        return Graphics::save_screenshot(self, file_path);
     }
 
 }
@@ -2,11 +2,14 @@
 // Application
 //
 
-use std::{sync::atomic::{AtomicBool, Ordering}};
+use std::{rc::Rc, sync::atomic::{AtomicBool, Ordering}};
 
 use crate::graphics::{
-    graphics::{self, Graphics},
-    api::{Api}
+    graphics::{self, Graphics, GraphicsMode},
+    api::{Api},
+    backend::Backend,
+    error::GamekitError,
+    shader_watcher
 };
 
 use super::graphics::GraphicsContext;
@@ -14,7 +17,7 @@ use super::graphics::GraphicsContext;
 defaults!();
 
 pub trait Executor {
-    fn new(api: &mut dyn Api) -> Result<Self, String> where Self: Sized;
+    fn new(api: &mut dyn Api) -> Result<Self, GamekitError> where Self: Sized;
     fn initialize(&mut self, api: &mut dyn Api);
     fn free(&mut self, api: &mut dyn Api);
     fn update_layout(&mut self, api: &mut dyn Api);
@@ -47,13 +50,37 @@ pub struct ApplicationContext {
 pub struct Application<Exec: Executor> {
     configuration: Configuration,
     state: State,
-    engine: graphics::Graphics,
-    executor: Exec
+    executor: Exec, // dropped before the engine, its resources still need the backend
+    engine: graphics::Graphics
 }
 
 impl<Exec: Executor> Application<Exec> {
 
-    pub fn new(frame_rate: i32) -> Result<Application<Exec>, String> {
+    pub fn new(frame_rate: i32) -> Result<Application<Exec>, GamekitError> {
+        return Self::with_mode(frame_rate, GraphicsMode::Window);
+    }
+
+    pub fn with_mode(frame_rate: i32, mode: GraphicsMode) -> Result<Application<Exec>, GamekitError> {
+        return Self::create(frame_rate, Graphics::new(mode)?, Exec::new);
+    }
+
+    // creates the executor with factory instead of Executor::new, e.g. to hand
+    // over configuration:
+    //
+    //     let app = Application::with_factory(60, GraphicsMode::Window, |api| ShadertoyExecutor::with_config(api, &config))?;
+    pub fn with_factory<F>(frame_rate: i32, mode: GraphicsMode, factory: F) -> Result<Application<Exec>, GamekitError>
+        where F: FnOnce(&mut dyn Api) -> Result<Exec, GamekitError> {
+        return Self::create(frame_rate, Graphics::new(mode)?, factory);
+    }
+
+    // runs without a device context on the given backend, e.g. a RecordingBackend
+    // to inspect the commands issued by the executor
+    pub fn with_backend(frame_rate: i32, backend: Rc<dyn Backend>, width: u32, height: u32) -> Result<Application<Exec>, GamekitError> {
+        return Self::create(frame_rate, Graphics::with_backend(backend, width, height), Exec::new);
+    }
+
+    fn create<F>(frame_rate: i32, mut engine: Graphics, factory: F) -> Result<Application<Exec>, GamekitError>
+        where F: FnOnce(&mut dyn Api) -> Result<Exec, GamekitError> {
 
         let configuration = Configuration {
             frame_rate
@@ -73,14 +100,13 @@ impl<Exec: Executor> Application<Exec> {
             avg_frames_per_second: 0.0,
         };
 
-        let mut engine = Graphics::new()?;
-        let executor = Exec::new(&mut engine as &mut dyn Api)?;
+        let executor = factory(&mut engine as &mut dyn Api)?;
 
         let mut app = Application {
             configuration,
             state,
-            engine,
-            executor
+            executor,
+            engine
         };
 
         app.initialize();
@@ -176,6 +202,8 @@ impl<Exec: Executor> Application<Exec> {
             return true; // ok to not draw
         }
 
+        shader_watcher::poll();
+
         if !self.engine.begin_draw() {
             return false;
         }
@@ -192,6 +220,41 @@ impl<Exec: Executor> Application<Exec> {
         return true;
     }
 
+    pub fn graphics(&self) -> &Graphics {
+        return &self.engine;
+    }
+
+    // deterministic stepping with a fixed time delta, e.g. for tests
+    pub fn run_frames(&mut self, frames: u64, delta_seconds: f32) -> bool {
+
+        let delta_micros = (delta_seconds as f64 * 1000000.0) as i64;
+
+        for _ in 0..frames {
+
+            if !self.engine.process_events() {
+                return false;
+            }
+
+            {
+                let state = &mut self.state;
+                state.time_micros += delta_micros;
+                state.time_seconds = (state.time_micros as f64 / 1000000.0) as f32;
+                state.delta_micros = delta_micros;
+                state.delta_seconds = delta_seconds;
+            }
+
+            if !self.update_state(delta_seconds) {
+                return false;
+            }
+
+            if !self.update_graphics() {
+                return false;
+            }
+        }
+
+        return true;
+    }
+
     pub fn get_context(&self) -> ApplicationContext {
         ApplicationContext {
             _engine_context: self.engine.get_context()
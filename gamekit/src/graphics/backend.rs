@@ -0,0 +1,267 @@
+//
+// Backend
+//
+
+use std::{alloc::{self, Layout}, cell::RefCell, hash::{Hash, Hasher}, rc::Rc};
+
+use super::{
+    api::{BlendMode, MemoryBarrier},
+    primitives::DrawMode,
+    buffers::{BufferType, BufferUsage},
+    error::GamekitError,
+    program_cache,
+    reflection::ProgramInterface,
+    shader::ShaderType,
+    types::{Vector2, Vector3, Vector4, Vector2i, Matrix2, Matrix3, Matrix4}
+};
+
+defaults!();
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFormat {
+    Rgb8,
+    Rgba8,
+    Rgba16F
+}
+
+impl TextureFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            TextureFormat::Rgb8 => 3,
+            TextureFormat::Rgba8 => 4,
+            TextureFormat::Rgba16F => 8
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DepthStencilFormat {
+    Depth24,
+    Depth32F,
+    Depth24Stencil8
+}
+
+impl DepthStencilFormat {
+    pub fn has_stencil(&self) -> bool {
+        return *self == DepthStencilFormat::Depth24Stencil8;
+    }
+}
+
+#[derive(Clone, Hash)]
+pub enum ShaderCode<'a> {
+    // SPIR-V with the entry point and (constant id, value) specialization constants
+    Binary(&'a [u8], &'a str, &'a [(u32, u32)]),
+    Source(&'a str)
+}
+
+impl ShaderCode<'_> {
+
+    // stable across runs, used as part of program cache keys
+    pub fn stable_hash(&self) -> u64 {
+        let mut hasher = program_cache::Fnv1a::new();
+        Hash::hash(self, &mut hasher);
+        return hasher.finish();
+    }
+
+}
+
+// component type of vertex attributes in the buffer, normalized bytes are
+// read as floats in [0, 1] (unsigned) or [-1, 1] (signed), integers are
+// read as int/uint by the shader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeType {
+    Float,
+    Int,
+    UInt,
+    NormalizedByte,
+    NormalizedUByte
+}
+
+impl AttributeType {
+    pub fn size(&self) -> usize {
+        return match self {
+            AttributeType::Float | AttributeType::Int | AttributeType::UInt => 4,
+            AttributeType::NormalizedByte | AttributeType::NormalizedUByte => 1
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub components: u32,
+    pub data_type: AttributeType,
+    pub offset: usize
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2(Vector2),
+    Vec3(Vector3),
+    Vec4(Vector4),
+    Int(i32),
+    IVec2(Vector2i),
+    UInt(u32),
+    Bool(bool),
+    Mat2(Matrix2),
+    Mat3(Matrix3),
+    Mat4(Matrix4),
+    // texture unit of a sampler
+    Sampler(u32)
+}
+
+impl UniformValue {
+
+    // uniform arrays are uploaded with one glUniform*v call, so all elements need the same kind
+    pub fn is_homogeneous(values: &[UniformValue]) -> bool {
+        return match values.first() {
+            Some(first) => values.iter().all(|value| std::mem::discriminant(value) == std::mem::discriminant(first)),
+            None => false
+        };
+    }
+
+}
+
+pub trait Backend {
+
+    // device state
+    fn initialize(&self);
+    fn viewport(&self, x: u32, y: u32, width: u32, height: u32);
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32);
+    fn clear(&self);
+    fn set_blend_mode(&self, blend_mode: BlendMode);
+    // x/y from the top of the bound framebuffer, which is framebuffer_height pixels high
+    fn read_pixels(&self, x: u32, y: u32, width: u32, height: u32, framebuffer_height: u32) -> Vec<u8>;
+
+    // draw calls
+    fn draw_arrays(&self, mode: DrawMode, ofs: usize, count: usize);
+    fn draw_elements(&self, mode: DrawMode, count: usize);
+    fn draw_elements_instanced(&self, mode: DrawMode, count: usize, num_instances: usize);
+
+    // compute, the indirect dispatch reads from the bound DispatchIndirectBuffer
+    fn dispatch_compute(&self, x: u32, y: u32, z: u32);
+    fn dispatch_compute_indirect(&self, offset: usize);
+    fn memory_barrier(&self, barriers: &[MemoryBarrier]);
+
+    // buffers, data is uploaded by buffer id and leaves the bindings untouched
+    fn create_buffer(&self) -> u32;
+    fn delete_buffer(&self, id: u32);
+    fn bind_buffer(&self, id: u32, buffer_type: &BufferType);
+    fn bind_buffer_base(&self, id: u32, binding_point: u32, buffer_type: &BufferType);
+    fn unbind_buffer(&self, id: u32, buffer_type: &BufferType);
+    fn buffer_data(&self, id: u32, buffer_usage: &BufferUsage, data: &[u8]);
+    fn buffer_sub_data(&self, id: u32, offset: usize, data: &[u8]);
+    // zeroed storage of size bytes, mapped for writing until the buffer is
+    // deleted, writes are visible to later commands without flushing
+    fn create_mapped_buffer(&self, size: usize) -> Result<(u32, *mut u8), GamekitError>;
+
+    // fences after the commands issued so far, 0 where commands complete immediately
+    fn fence_sync(&self) -> u64;
+    // blocks until the commands before the fence are complete
+    fn wait_fence(&self, fence: u64);
+    fn delete_fence(&self, fence: u64);
+
+    // vertex arrays, configured by id without binding them. Attributes read
+    // from one of the buffer binding slots of the array, starting at the byte
+    // offset of the slot. The divisor of a slot makes its attributes advance
+    // per vertex (0) or per n instances.
+    fn create_vertex_array(&self) -> u32;
+    fn delete_vertex_array(&self, id: u32);
+    fn bind_vertex_array(&self, id: u32);
+    fn unbind_vertex_array(&self);
+    fn vertex_array_attribute(&self, vertex_array: u32, binding: u32, attribute: &VertexAttribute);
+    fn vertex_array_buffer(&self, vertex_array: u32, binding: u32, buffer: u32, offset: usize, stride: usize, divisor: u32);
+    fn vertex_array_index_buffer(&self, vertex_array: u32, buffer: u32);
+
+    // textures
+    fn create_texture(&self, width: u32, height: u32, format: TextureFormat, pixels: &[u8]) -> Result<u32, GamekitError>;
+    fn delete_texture(&self, id: u32);
+    fn bind_texture(&self, id: u32, bind_location: u32);
+    fn unbind_texture(&self, bind_location: u32);
+
+    // render targets, framebuffer 0 is the default framebuffer of the surface
+    fn create_render_texture(&self, width: u32, height: u32, format: TextureFormat) -> Result<u32, GamekitError>;
+    fn create_renderbuffer(&self, width: u32, height: u32, format: DepthStencilFormat) -> Result<u32, GamekitError>;
+    fn delete_renderbuffer(&self, id: u32);
+    fn create_framebuffer(&self, color_attachments: &[u32], depth_stencil: Option<(u32, DepthStencilFormat)>) -> Result<u32, GamekitError>;
+    fn delete_framebuffer(&self, id: u32);
+    fn bind_framebuffer(&self, id: u32);
+    fn read_framebuffer(&self, id: u32, attachment: u32, width: u32, height: u32) -> Vec<u8>;
+
+    // shaders and programs
+    fn supports_spirv(&self) -> bool;
+    fn create_shader(&self, shader_type: ShaderType, code: ShaderCode) -> Result<u32, GamekitError>;
+    fn delete_shader(&self, id: u32);
+    fn create_program(&self, shaders: &[u32]) -> Result<u32, GamekitError>;
+    // linked program as (format, data), None if the driver offers no binary formats
+    fn program_binary(&self, program: u32) -> Option<(u32, Vec<u8>)>;
+    fn create_program_from_binary(&self, format: u32, binary: &[u8]) -> Result<u32, GamekitError>;
+    // vendor, renderer and version, program binaries are only valid for the same driver
+    fn driver_id(&self) -> String;
+    fn delete_program(&self, id: u32);
+    fn use_program(&self, id: u32);
+    fn current_program(&self) -> u32;
+    fn program_interface(&self, program: u32) -> ProgramInterface;
+    fn get_uniform_location(&self, program: u32, name: &str) -> i32;
+    // uniforms are set on the given program, which does not have to be current
+    fn set_uniform(&self, program: u32, location: i32, value: UniformValue);
+    // consecutive elements of a uniform array starting at location, all of the same kind
+    fn set_uniform_array(&self, program: u32, location: i32, values: &[UniformValue]);
+
+}
+
+// host memory standing in for mapped buffers in backends without a GPU,
+// aligned like GL mappings (GL_MIN_MAP_BUFFER_ALIGNMENT)
+pub(crate) struct HostMapping {
+    data: *mut u8,
+    layout: Layout
+}
+
+impl HostMapping {
+
+    const ALIGNMENT: usize = 64;
+
+    pub(crate) fn new(size: usize) -> HostMapping {
+        let layout = Layout::from_size_align(size.max(1), Self::ALIGNMENT).expect("mapped buffer too large");
+        let data = unsafe { alloc::alloc_zeroed(layout) };
+        if data.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        return HostMapping { data, layout };
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        return self.data;
+    }
+
+    pub(crate) fn bytes(&self) -> &[u8] {
+        return unsafe { std::slice::from_raw_parts(self.data, self.layout.size()) };
+    }
+
+}
+
+impl Drop for HostMapping {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.data, self.layout); }
+    }
+}
+
+thread_local! {
+    static CURRENT_BACKEND: RefCell<Option<Rc<dyn Backend>>> = RefCell::new(None);
+}
+
+pub fn set_current(backend: Rc<dyn Backend>) {
+    CURRENT_BACKEND.with(|current| {
+        *current.borrow_mut() = Some(backend);
+    });
+}
+
+pub fn current() -> Rc<dyn Backend> {
+    return CURRENT_BACKEND.with(|current| {
+        match current.borrow().as_ref() {
+            Some(backend) => backend.clone(),
+            None => panic!("no graphics backend is active on this thread")
+        }
+    });
+}
@@ -1,17 +1,23 @@
-use crate::graphics::types::Vertex;
+use std::{cell::Cell, marker::PhantomData};
 
-use super::{gl, types::Quad};
+use crate::graphics::{types::Vertex, vertex_layout::VertexLayout};
+
+use super::{backend, types::Quad, error::GamekitError, layout::{self, BlockLayout, Layout}};
 
 defaults!();
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BufferType {
     ArrayBuffer,
     IndexBuffer,
     UniformBuffer,
-    ShaderStorageBuffer
+    ShaderStorageBuffer,
+    // work group counts for ComputeProgram::dispatch_indirect
+    DispatchIndirectBuffer
 }
 
 #[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BufferUsage {
     StreamDraw,
     StreamRead,
@@ -32,7 +38,7 @@ pub struct BufferObject {
 
 impl BufferObject {
 
-    pub fn new(buffer_type: BufferType, buffer_usage: BufferUsage) -> Result<BufferObject, String> {
+    pub fn new(buffer_type: BufferType, buffer_usage: BufferUsage) -> Result<BufferObject, GamekitError> {
 
         let buffer = BufferObject {
             id: create_buffer(),
@@ -43,6 +49,10 @@ impl BufferObject {
         return Ok(buffer);
     }
 
+    pub fn id(&self) -> u32 {
+        return self.id;
+    }
+
     pub fn bind(&self) {
         bind_buffer(self.id, &self.buffer_type);
     }
@@ -58,44 +68,22 @@ impl BufferObject {
         }
     }
 
-    pub fn copy_vertices_to_buffer(&mut self, data: &Vec<Vertex>, count: usize) {
-
-        self.bind();
-
-        copy_vertices_to_buffer(&self.buffer_type, &self.buffer_usage, data, count);
-
-        self.unbind();
+    // uploads replace the buffer contents by id, bindings are left untouched
 
+    pub fn copy_vertices_to_buffer<V: VertexLayout>(&mut self, data: &[V], count: usize) {
+        copy_vertices_to_buffer(self.id, &self.buffer_usage, data, count);
     }
 
     pub fn copy_quads_to_buffer(&mut self, data: &Vec<Quad>, count: usize) {
-
-        self.bind();
-
-        copy_quads_to_buffer(&self.buffer_type, &self.buffer_usage, data, count);
-
-        self.unbind();
-
+        copy_quads_to_buffer(self.id, &self.buffer_usage, data, count);
     }
 
     pub fn copy_u32_to_buffer(&self, data: &Vec<u32>, count: usize) {
-
-        self.bind();
-
-        copy_u32_to_buffer(&self.buffer_type, &self.buffer_usage, data, count);
-
-        self.unbind();
-
+        copy_u32_to_buffer(self.id, &self.buffer_usage, data, count);
     }
 
     pub fn copy_to_buffer(&mut self, data_ptr: *const u8, data_size: usize) {
-
-        self.bind();
-
-        copy_to_buffer(&self.buffer_type, &self.buffer_usage, data_ptr, data_size);
-
-        self.unbind();
-
+        copy_to_buffer(self.id, &self.buffer_usage, data_ptr, data_size);
     }
 
 }
@@ -107,50 +95,61 @@ impl Drop for BufferObject {
     }
 }
 
-pub struct ShaderStorageBufferObject {
+// Shader storage block holding a T, std430 unless given otherwise:
+//
+//     let mut buffer: ShaderStorageBufferObject<ShaderData> = ShaderStorageBufferObject::new(1, BufferUsage::DynamicDraw)?;
+//     buffer.copy_to_buffer(&data);
+pub struct ShaderStorageBufferObject<T: BlockLayout> {
     id: u32,
     binding_point: u32,
     buffer_usage: BufferUsage,
-    initialized: bool
+    layout: Layout,
+    initialized: bool,
+    phantom: PhantomData<T>
 }
 
-impl ShaderStorageBufferObject {
-    pub fn new(binding_point: u32, buffer_usage: BufferUsage) -> Result<ShaderStorageBufferObject, String> {
+impl<T: BlockLayout> ShaderStorageBufferObject<T> {
+    pub fn new(binding_point: u32, buffer_usage: BufferUsage) -> Result<ShaderStorageBufferObject<T>, GamekitError> {
+        return Self::with_layout(binding_point, buffer_usage, Layout::Std430);
+    }
+
+    pub fn with_layout(binding_point: u32, buffer_usage: BufferUsage, layout: Layout) -> Result<ShaderStorageBufferObject<T>, GamekitError> {
 
         let buffer = ShaderStorageBufferObject {
             id: create_buffer(),
             binding_point,
             buffer_usage,
-            initialized: false
+            layout,
+            initialized: false,
+            phantom: PhantomData
         };
 
         return Ok(buffer);
     }
 
+    pub fn layout(&self) -> Layout {
+        return self.layout;
+    }
+
+    // attaches the buffer to its binding point
     pub fn bind(&self) {
-        if !self.initialized {
-            bind_buffer(self.id, &BufferType::ShaderStorageBuffer);
-            bind_buffer_base(self.id, self.binding_point, &BufferType::ShaderStorageBuffer);
-        } else {
-            bind_buffer(self.id, &BufferType::ShaderStorageBuffer);
-        }
+        bind_buffer_base(self.id, self.binding_point, &BufferType::ShaderStorageBuffer);
     }
 
     pub fn unbind(&self) {
         unbind_buffer(self.id, &BufferType::ShaderStorageBuffer);
     }
 
-    pub fn copy_to_buffer(&mut self, data_ptr: *const u8, data_size: usize) {
-        self.bind();
+    pub fn copy_to_buffer(&mut self, data: &T) {
+
+        let data = layout::to_bytes(data, self.layout);
 
         if !self.initialized {
-            copy_to_buffer(&BufferType::ShaderStorageBuffer, &self.buffer_usage, data_ptr, data_size);
+            backend::current().buffer_data(self.id, &self.buffer_usage, &data);
             self.initialized = true;
         } else {
-            copy_to_buffer_sub(&BufferType::ShaderStorageBuffer, data_ptr, 0, data_size);
+            backend::current().buffer_sub_data(self.id, 0, &data);
         }
-
-        self.unbind();
     }
 
     pub fn free(&mut self) {
@@ -161,56 +160,60 @@ impl ShaderStorageBufferObject {
     }
 }
 
-impl Drop for ShaderStorageBufferObject {
+impl<T: BlockLayout> Drop for ShaderStorageBufferObject<T> {
     fn drop(&mut self) {
         debug!("drop shader storage buffer object");
         self.free();
     }
 }
 
-pub struct UniformBufferObject {
+// Uniform block holding a T in std140 layout
+pub struct UniformBufferObject<T: BlockLayout> {
     id: u32,
     binding_point: u32,
     buffer_usage: BufferUsage,
-    initialized: bool
+    initialized: bool,
+    phantom: PhantomData<T>
 }
 
-impl UniformBufferObject {
-    pub fn new(binding_point: u32, buffer_usage: BufferUsage) -> Result<UniformBufferObject, String> {
+impl<T: BlockLayout> UniformBufferObject<T> {
+    pub fn new(binding_point: u32, buffer_usage: BufferUsage) -> Result<UniformBufferObject<T>, GamekitError> {
 
         let buffer = UniformBufferObject {
             id: create_buffer(),
             binding_point,
             buffer_usage,
-            initialized: false
+            initialized: false,
+            phantom: PhantomData
         };
 
         return Ok(buffer);
     }
 
+    // attaches the buffer to its binding point, needed again when
+    // several buffers share one binding point
+    pub fn bind_base(&self) {
+        bind_buffer_base(self.id, self.binding_point, &BufferType::UniformBuffer);
+    }
+
     pub fn bind(&self) {
-        if !self.initialized {
-            bind_buffer_base(self.id, self.binding_point, &BufferType::UniformBuffer);
-        } else {
-            bind_buffer(self.id, &BufferType::UniformBuffer);
-        }
+        self.bind_base();
     }
 
     pub fn unbind(&self) {
         unbind_buffer(self.id, &BufferType::UniformBuffer);
     }
 
-    pub fn copy_to_buffer(&mut self, data_ptr: *const u8, data_size: usize) {
-        self.bind();
+    pub fn copy_to_buffer(&mut self, data: &T) {
+
+        let data = layout::to_bytes(data, Layout::Std140);
 
         if !self.initialized {
-            copy_to_buffer(&BufferType::UniformBuffer, &self.buffer_usage, data_ptr, data_size);
+            backend::current().buffer_data(self.id, &self.buffer_usage, &data);
             self.initialized = true;
         } else {
-            copy_to_buffer_sub(&BufferType::UniformBuffer, data_ptr, 0, data_size);
+            backend::current().buffer_sub_data(self.id, 0, &data);
         }
-
-        self.unbind();
     }
 
     pub fn free(&mut self) {
@@ -222,81 +225,288 @@ impl UniformBufferObject {
 }
 
 
-impl Drop for UniformBufferObject {
+impl<T: BlockLayout> Drop for UniformBufferObject<T> {
     fn drop(&mut self) {
         debug!("drop uniform buffer object");
         self.free();
     }
 }
 
-pub struct VertexArray {
+// regions of a RingBuffer, the CPU fills one while the GPU may still
+// read the two before
+pub const RING_BUFFER_REGIONS: usize = 3;
+
+// Persistently mapped buffer of RING_BUFFER_REGIONS regions holding capacity
+// values of T each. Values are written straight into GPU-visible memory, a
+// fence per region keeps the CPU from overwriting what pending draws read:
+//
+//     let mut ring: RingBuffer<Vertex> = RingBuffer::new(4096)?;
+//     let vertex_array = VertexArray::with_ring_buffer(&ring, 0)?;
+//     ring.begin();
+//     ring.region()[0] = vertex;
+//     vertex_array.set_ring_region(&ring);
+//     // bind and draw
+//     ring.fence();
+pub struct RingBuffer<T: VertexLayout> {
     id: u32,
-    buffer_object: BufferObject
+    data: *mut T,
+    capacity: usize,
+    region: usize,
+    fences: [Cell<u64>; RING_BUFFER_REGIONS]
 }
 
-impl VertexArray {
+impl<T: VertexLayout> RingBuffer<T> {
 
-    pub fn new(usage: BufferUsage, start_index: u32) -> Result<VertexArray, String> {
+    pub fn new(capacity: usize) -> Result<RingBuffer<T>, GamekitError> {
 
-        let buffer_object = BufferObject::new(BufferType::ArrayBuffer, usage)?;
+        let size = (capacity * std::mem::size_of::<T>() * RING_BUFFER_REGIONS).max(1);
+        let (id, data) = backend::current().create_mapped_buffer(size)?;
+
+        if data as usize % std::mem::align_of::<T>() != 0 {
+            delete_buffer(id);
+            return Err(GamekitError::invalid_argument("mapped buffer is not aligned for its values"));
+        }
+
+        let buffer = RingBuffer {
+            id,
+            data: data as *mut T,
+            capacity,
+            // the first begin moves to region 0
+            region: RING_BUFFER_REGIONS - 1,
+            fences: Default::default()
+        };
+
+        return Ok(buffer);
+    }
 
-        let mut id: gl::types::GLuint = 0;
+    pub fn id(&self) -> u32 {
+        return self.id;
+    }
+
+    // values per region
+    pub fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+
+    // byte offset of the current region in the buffer
+    pub fn region_offset(&self) -> usize {
+        return self.region * self.capacity * std::mem::size_of::<T>();
+    }
 
-        unsafe {
-            gl::GenVertexArrays(1, &mut id);
+    // moves on to the next region, waits until the draws reading it are complete
+    pub fn begin(&mut self) {
+        self.region = (self.region + 1) % RING_BUFFER_REGIONS;
+
+        let fence = self.fences[self.region].replace(0);
+        if fence != 0 {
+            let backend = backend::current();
+            backend.wait_fence(fence);
+            backend.delete_fence(fence);
         }
+    }
+
+    // the current region, old contents are left as they were
+    pub fn region(&mut self) -> &mut [T] {
+        return unsafe { std::slice::from_raw_parts_mut(self.data.add(self.region * self.capacity), self.capacity) };
+    }
+
+    // call after the last draw reading the current region
+    pub fn fence(&self) {
+        let backend = backend::current();
+        let fence = self.fences[self.region].replace(backend.fence_sync());
+        backend.delete_fence(fence);
+    }
+
+    pub fn free(&mut self) {
+        let backend = backend::current();
+        for fence in &self.fences {
+            backend.delete_fence(fence.replace(0));
+        }
+
+        if self.id != 0 {
+            // deleting the buffer unmaps it
+            delete_buffer(self.id);
+            self.id = 0;
+            self.data = std::ptr::null_mut();
+            self.capacity = 0;
+        }
+    }
+
+}
+
+impl<T: VertexLayout> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        debug!("drop ring buffer");
+        self.free();
+    }
+}
+
+// Vertex array reading V from its vertex buffer. More buffers can be attached,
+// e.g. per-instance data, and the index buffer is part of the array state.
+// Setting up and filling the array does not change the current bindings:
+//
+//     let mut mesh: VertexArray<MeshVertex> = VertexArray::new(BufferUsage::StaticDraw, 0)?;
+//     let instances = mesh.add_buffer::<InstanceData>(BufferUsage::DynamicDraw, 4, 1)?;
+//     mesh.copy_vertices_to_buffer(&vertices, vertices.len());
+//     mesh.copy_to_buffer(instances, &instance_data)?;
+//     mesh.set_indices(&indices, BufferUsage::StaticDraw)?;
+//
+// With a RingBuffer as vertex buffer the array reads from the ring region
+// given by set_ring_region.
+pub struct VertexArray<V: VertexLayout = Vertex> {
+    id: u32,
+    // None when reading from a RingBuffer
+    buffer_object: Option<BufferObject>,
+    // buffer binding slot n + 1, slot 0 is the vertex buffer
+    buffers: Vec<BufferObject>,
+    index_buffer: Option<BufferObject>,
+    phantom: PhantomData<V>
+}
+
+// buffer added with VertexArray::add_buffer, holding W values, only valid
+// for the array that returned it
+#[derive(Clone, Copy, Debug)]
+pub struct VertexBufferSlot<W: VertexLayout> {
+    vertex_array: u32,
+    index: usize,
+    phantom: PhantomData<W>
+}
+
+impl<V: VertexLayout> VertexArray<V> {
+
+    pub fn new(usage: BufferUsage, start_location: u32) -> Result<VertexArray<V>, GamekitError> {
+
+        let buffer_object = BufferObject::new(BufferType::ArrayBuffer, usage)?;
+
+        let id = backend::current().create_vertex_array();
+
+        V::declare_attributes(id, 0, start_location);
+        backend::current().vertex_array_buffer(id, 0, buffer_object.id(), 0, std::mem::size_of::<V>(), 0);
 
         let vertex_array = VertexArray {
             id,
-            buffer_object
+            buffer_object: Some(buffer_object),
+            buffers: Vec::new(),
+            index_buffer: None,
+            phantom: PhantomData
         };
 
-        vertex_array.bind();
-        vertex_array.buffer_object.bind();
+        return Ok(vertex_array);
+    }
 
-        Vertex::declare_attrib_pointers(start_index);
+    pub fn with_ring_buffer(ring: &RingBuffer<V>, start_location: u32) -> Result<VertexArray<V>, GamekitError> {
+
+        let id = backend::current().create_vertex_array();
+
+        let vertex_array = VertexArray {
+            id,
+            buffer_object: None,
+            buffers: Vec::new(),
+            index_buffer: None,
+            phantom: PhantomData
+        };
 
-        vertex_array.buffer_object.unbind();
-        vertex_array.unbind();
+        V::declare_attributes(id, 0, start_location);
+        vertex_array.set_ring_region(ring);
 
         return Ok(vertex_array);
     }
 
-    pub fn copy_vertices_to_buffer(&mut self, data: &Vec<Vertex>, count: usize) {
-        self.buffer_object.copy_vertices_to_buffer(data, count);
+    // reads the vertices from the current region of the ring
+    pub fn set_ring_region(&self, ring: &RingBuffer<V>) {
+        backend::current().vertex_array_buffer(self.id, 0, ring.id(), ring.region_offset(), std::mem::size_of::<V>(), 0);
     }
 
-    pub fn copy_quads_to_buffer(&mut self, data: &Vec<Quad>, count: usize) {
-        self.buffer_object.copy_quads_to_buffer(data, count);
+    // attaches a buffer of W, divisor 0 advances the attributes per vertex,
+    // n once per n instances
+    pub fn add_buffer<W: VertexLayout>(&mut self, usage: BufferUsage, start_location: u32, divisor: u32) -> Result<VertexBufferSlot<W>, GamekitError> {
+
+        let buffer_object = BufferObject::new(BufferType::ArrayBuffer, usage)?;
+        let binding = self.buffers.len() as u32 + 1;
+
+        W::declare_attributes(self.id, binding, start_location);
+        backend::current().vertex_array_buffer(self.id, binding, buffer_object.id(), 0, std::mem::size_of::<W>(), divisor);
+
+        self.buffers.push(buffer_object);
+
+        return Ok(VertexBufferSlot { vertex_array: self.id, index: self.buffers.len() - 1, phantom: PhantomData });
     }
 
-    pub fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.id);
+    pub fn copy_vertices_to_buffer(&mut self, data: &[V], count: usize) {
+        match &mut self.buffer_object {
+            Some(buffer_object) => buffer_object.copy_vertices_to_buffer(data, count),
+            None => warn!("vertices of a ring buffer are written to the ring")
         }
     }
 
-    pub fn unbind(&self) {
-        unsafe {
-            gl::BindVertexArray(0);
+    pub fn copy_to_buffer<W: VertexLayout>(&mut self, slot: VertexBufferSlot<W>, data: &[W]) -> Result<(), GamekitError> {
+
+        let buffer = match self.buffers.get_mut(slot.index) {
+            Some(buffer) if slot.vertex_array == self.id => buffer,
+            _ => { return Err(GamekitError::invalid_argument("vertex buffer slot belongs to another vertex array")); }
+        };
+
+        buffer.copy_vertices_to_buffer(data, data.len());
+
+        return Ok(());
+    }
+
+    // replaces the index buffer, draw with Api::draw_elements while the array is bound
+    pub fn set_indices(&mut self, indices: &[u32], usage: BufferUsage) -> Result<(), GamekitError> {
+
+        if self.index_buffer.is_none() {
+            let index_buffer = BufferObject::new(BufferType::IndexBuffer, usage)?;
+            backend::current().vertex_array_index_buffer(self.id, index_buffer.id());
+            self.index_buffer = Some(index_buffer);
+        }
+
+        if let Some(index_buffer) = &self.index_buffer {
+            copy_u32_to_buffer(index_buffer.id(), &usage, indices, indices.len());
         }
+
+        return Ok(());
+    }
+
+    pub fn bind(&self) {
+        backend::current().bind_vertex_array(self.id);
+    }
+
+    pub fn unbind(&self) {
+        backend::current().unbind_vertex_array();
     }
 
     pub fn free(&mut self) {
         info!("Vertex array free");
         if self.id != 0 {
-            unsafe {
-                gl::DeleteVertexArrays(1, &mut self.id);
-            }
+            backend::current().delete_vertex_array(self.id);
             self.id = 0;
         }
 
-        self.buffer_object.free();
+        if let Some(buffer_object) = &mut self.buffer_object {
+            buffer_object.free();
+        }
+
+        for buffer in &mut self.buffers {
+            buffer.free();
+        }
+
+        if let Some(index_buffer) = &mut self.index_buffer {
+            index_buffer.free();
+        }
     }
 
 }
 
-impl Drop for VertexArray {
+impl VertexArray<Vertex> {
+    pub fn copy_quads_to_buffer(&mut self, data: &Vec<Quad>, count: usize) {
+        match &mut self.buffer_object {
+            Some(buffer_object) => buffer_object.copy_quads_to_buffer(data, count),
+            None => warn!("vertices of a ring buffer are written to the ring")
+        }
+    }
+}
+
+impl<V: VertexLayout> Drop for VertexArray<V> {
     fn drop(&mut self) {
         debug!("drop vertex array");
         self.free();
@@ -304,112 +514,50 @@ impl Drop for VertexArray {
 }
 
 fn create_buffer() -> u32 {
-    let mut id: gl::types::GLuint = 0;
-    unsafe { gl::GenBuffers(1, &mut id); };
-    return id as u32;
+    return backend::current().create_buffer();
 }
 
 fn delete_buffer(id: u32) {
     if id != 0 {
-        unsafe {
-            let mut buffer_id = id;
-            gl::DeleteBuffers(1, &mut buffer_id);
-        }
+        backend::current().delete_buffer(id);
     }
 }
 
 fn bind_buffer(id: u32, buffer_type: &BufferType) {
-    unsafe {
-        gl::BindBuffer(map_type(buffer_type), id);
-    }
+    backend::current().bind_buffer(id, buffer_type);
 }
 
 fn bind_buffer_base(id: u32, binding_point: u32, buffer_type: &BufferType) {
-    unsafe {
-        gl::BindBufferBase(map_type(buffer_type), binding_point, id);
-    }
-}
-
-fn unbind_buffer(_id: u32, buffer_type: &BufferType) {
-    unsafe {
-        gl::BindBuffer(map_type(buffer_type), 0);
-    }
+    backend::current().bind_buffer_base(id, binding_point, buffer_type);
 }
 
-fn map_usage(usage: &BufferUsage) -> gl::types::GLenum {
-    match usage {
-        BufferUsage::StreamDraw => gl::STREAM_DRAW,
-        BufferUsage::StreamRead => gl::STREAM_READ,
-        BufferUsage::StreamCopy => gl::STREAM_COPY,
-        BufferUsage::StaticDraw => gl::STATIC_DRAW,
-        BufferUsage::StaticRead => gl::STATIC_READ,
-        BufferUsage::StaticCopy => gl::STATIC_COPY,
-        BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
-        BufferUsage::DynamicRead => gl::DYNAMIC_READ,
-        BufferUsage::DynamicCopy => gl::DYNAMIC_COPY,
-    }
-}
-
-fn map_type(buffer_type: &BufferType) -> gl::types::GLenum  {
-    match buffer_type {
-        BufferType::ArrayBuffer => gl::ARRAY_BUFFER,
-        BufferType::IndexBuffer => gl::ELEMENT_ARRAY_BUFFER,
-        BufferType::UniformBuffer => gl::UNIFORM_BUFFER,
-        BufferType::ShaderStorageBuffer => gl::SHADER_STORAGE_BUFFER
-    }
+fn unbind_buffer(id: u32, buffer_type: &BufferType) {
+    backend::current().unbind_buffer(id, buffer_type);
 }
 
-fn copy_vertices_to_buffer(buffer_type: &BufferType, buffer_usage: &BufferUsage, data: &Vec<Vertex>, count: usize) {
+fn copy_vertices_to_buffer<V: VertexLayout>(id: u32, buffer_usage: &BufferUsage, data: &[V], count: usize) {
 
     let data_ptr = data.as_ptr();
-    let data_size = count * std::mem::size_of::<Vertex>();
+    let data_size = count.min(data.len()) * std::mem::size_of::<V>();
 
-    copy_to_buffer(buffer_type, buffer_usage, data_ptr as *const u8, data_size);
+    copy_to_buffer(id, buffer_usage, data_ptr as *const u8, data_size);
 }
 
-fn copy_quads_to_buffer(buffer_type: &BufferType, buffer_usage: &BufferUsage, data: &Vec<Quad>, count: usize) {
+fn copy_quads_to_buffer(id: u32, buffer_usage: &BufferUsage, data: &Vec<Quad>, count: usize) {
 
     let data_ptr = data.as_ptr();
-    let data_size = count * std::mem::size_of::<Quad>();
+    let data_size = count.min(data.len()) * std::mem::size_of::<Quad>();
 
-    copy_to_buffer(buffer_type, buffer_usage, data_ptr as *const u8, data_size);
+    copy_to_buffer(id, buffer_usage, data_ptr as *const u8, data_size);
 }
 
-fn copy_u32_to_buffer(buffer_type: &BufferType, buffer_usage: &BufferUsage, data: &Vec<u32>, count: usize) {
+fn copy_u32_to_buffer(id: u32, buffer_usage: &BufferUsage, data: &[u32], count: usize) {
     let data_ptr = data.as_ptr();
-    let data_size = count * std::mem::size_of::<u32>();
-    copy_to_buffer(buffer_type, buffer_usage, data_ptr as *const u8, data_size);
+    let data_size = count.min(data.len()) * std::mem::size_of::<u32>();
+    copy_to_buffer(id, buffer_usage, data_ptr as *const u8, data_size);
 }
 
-fn copy_to_buffer(buffer_type: &BufferType, buffer_usage: &BufferUsage, data_ptr: *const u8, data_size: usize) {
-    let data_usage = map_usage(buffer_usage);
-    unsafe {
-        gl::BufferData(
-            map_type(buffer_type),
-            data_size as gl::types::GLsizeiptr,
-            data_ptr as *const gl::types::GLvoid,
-            data_usage
-        );
-    }
-}
-
-fn copy_to_buffer_sub(buffer_type: &BufferType, data_ptr: *const u8, offset: usize, data_size: usize) {
-    unsafe {
-        gl::BufferSubData(
-            map_type(buffer_type),
-            offset as gl::types::GLintptr,
-            data_size as gl::types::GLsizeiptr,
-            data_ptr as *const gl::types::GLvoid
-        );
-    }
-}
-
-#[allow(dead_code)]
-fn map_and_copy(buffer_type: &BufferType, data_ptr: *const u8, data_size: usize) {
-    unsafe {
-        let type_id = map_type(buffer_type);
-        let ptr = gl::MapBuffer(type_id, gl::WRITE_ONLY);
-        std::ptr::copy_nonoverlapping(data_ptr, ptr as *mut u8 , data_size);
-        gl::UnmapBuffer(type_id);
-    }
+fn copy_to_buffer(id: u32, buffer_usage: &BufferUsage, data_ptr: *const u8, data_size: usize) {
+    let data = unsafe { std::slice::from_raw_parts(data_ptr, data_size) };
+    backend::current().buffer_data(id, buffer_usage, data);
 }
@@ -0,0 +1,76 @@
+//
+// EGL
+//
+
+#![allow(non_camel_case_types, non_upper_case_globals, non_snake_case, dead_code, unused_imports, clippy::all)]
+
+use std::{ffi::CString, os::raw::{c_void, c_long}, sync::OnceLock};
+
+use super::error::GamekitError;
+
+pub type khronos_utime_nanoseconds_t = u64;
+pub type khronos_uint64_t = u64;
+pub type khronos_ssize_t = c_long;
+pub type EGLint = i32;
+pub type EGLNativeDisplayType = *const c_void;
+pub type EGLNativePixmapType = *const c_void;
+pub type EGLNativeWindowType = *const c_void;
+pub type NativeDisplayType = EGLNativeDisplayType;
+pub type NativePixmapType = EGLNativePixmapType;
+pub type NativeWindowType = EGLNativeWindowType;
+
+include!(concat!(env!("OUT_DIR"), "/egl_bindings.rs"));
+
+// libEGL is opened on first use instead of being linked, applications that
+// never create a headless context do not need it
+#[cfg(target_os = "linux")]
+const LIBRARY_NAMES: [&str; 2] = ["libEGL.so.1", "libEGL.so"];
+#[cfg(not(target_os = "linux"))]
+const LIBRARY_NAMES: [&str; 0] = [];
+
+static LOADED: OnceLock<bool> = OnceLock::new();
+
+// loads the EGL functions, the library stays loaded for the rest of the process
+pub fn load() -> Result<(), GamekitError> {
+
+    let loaded = *LOADED.get_or_init(|| {
+        for library_name in LIBRARY_NAMES {
+            let library_name = CString::new(library_name).unwrap();
+            let library = unsafe { sdl2::sys::SDL_LoadObject(library_name.as_ptr()) };
+            if library.is_null() {
+                continue;
+            }
+
+            load_with(|symbol| {
+                let symbol = CString::new(symbol).unwrap();
+                unsafe { sdl2::sys::SDL_LoadFunction(library, symbol.as_ptr()) as *const c_void }
+            });
+
+            return true;
+        }
+
+        return false;
+    });
+
+    if !loaded {
+        return Err(GamekitError::window("EGL is not available, headless contexts need libEGL"));
+    }
+
+    let required = [
+        GetDisplay::is_loaded(),
+        Initialize::is_loaded(),
+        BindAPI::is_loaded(),
+        CreateContext::is_loaded(),
+        MakeCurrent::is_loaded(),
+        GetProcAddress::is_loaded(),
+        GetError::is_loaded(),
+        DestroyContext::is_loaded(),
+        Terminate::is_loaded()
+    ];
+
+    if required.contains(&false) {
+        return Err(GamekitError::window("EGL library lacks required functions"));
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,104 @@
+//
+// Error
+//
+
+use std::{fmt, io};
+
+use super::preprocessor::IncludeError;
+
+#[derive(Debug)]
+pub enum GamekitError {
+    // reading or writing a file failed
+    Io { path: String, source: io::Error },
+    // an image file could not be decoded or has an unsupported layout
+    ImageDecode { path: String, message: String },
+    // an image could not be encoded
+    ImageEncode { path: String, message: String },
+    // the file is empty for shaders generated at runtime without a name
+    ShaderCompile { file: String, log: String },
+    ProgramLink { log: String },
+    // a GL call failed, the code is the GL error or framebuffer status
+    Gl { operation: String, code: u32 },
+    // SDL, window, display or context creation
+    Window(String),
+    // invalid parameters or configuration
+    InvalidArgument(String),
+    // a rendered image differs from its reference
+    ImageMismatch(String),
+    // a CPU struct differs from the layout a program expects
+    InterfaceMismatch(String)
+}
+
+impl GamekitError {
+
+    pub fn io(path: &str, source: io::Error) -> GamekitError {
+        return GamekitError::Io { path: path.to_string(), source };
+    }
+
+    pub fn image_decode(path: &str, message: impl fmt::Display) -> GamekitError {
+        return GamekitError::ImageDecode { path: path.to_string(), message: message.to_string() };
+    }
+
+    pub fn image_encode(path: &str, message: impl fmt::Display) -> GamekitError {
+        return GamekitError::ImageEncode { path: path.to_string(), message: message.to_string() };
+    }
+
+    pub fn gl(operation: &str, code: u32) -> GamekitError {
+        return GamekitError::Gl { operation: operation.to_string(), code };
+    }
+
+    pub fn window(message: impl fmt::Display) -> GamekitError {
+        return GamekitError::Window(message.to_string());
+    }
+
+    pub fn invalid_argument(message: impl fmt::Display) -> GamekitError {
+        return GamekitError::InvalidArgument(message.to_string());
+    }
+
+    // attaches the file name to compile errors reported by a backend
+    pub fn with_shader_file(self, file: &str) -> GamekitError {
+        return match self {
+            GamekitError::ShaderCompile { log, .. } => GamekitError::ShaderCompile { file: file.to_string(), log },
+            other => other
+        };
+    }
+
+}
+
+impl fmt::Display for GamekitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GamekitError::Io { path, source } => write!(f, "{}: {}", path, source),
+            GamekitError::ImageDecode { path, message } => write!(f, "failed to decode image {}: {}", path, message),
+            GamekitError::ImageEncode { path, message } => write!(f, "failed to encode image {}: {}", path, message),
+            GamekitError::ShaderCompile { file, log } => {
+                if file.is_empty() {
+                    write!(f, "failed to compile shader:\n{}", log)
+                } else {
+                    write!(f, "failed to compile shader {}:\n{}", file, log)
+                }
+            },
+            GamekitError::ProgramLink { log } => write!(f, "failed to link program:\n{}", log),
+            GamekitError::Gl { operation, code } => write!(f, "{} failed (0x{:x})", operation, code),
+            GamekitError::Window(message) => write!(f, "{}", message),
+            GamekitError::InvalidArgument(message) => write!(f, "{}", message),
+            GamekitError::ImageMismatch(message) => write!(f, "{}", message),
+            GamekitError::InterfaceMismatch(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+impl From<IncludeError> for GamekitError {
+    fn from(error: IncludeError) -> GamekitError {
+        return GamekitError::Io { path: error.path, source: error.error };
+    }
+}
+
+impl std::error::Error for GamekitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GamekitError::Io { source, .. } => Some(source),
+            _ => None
+        }
+    }
+}
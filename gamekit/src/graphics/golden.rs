@@ -0,0 +1,119 @@
+//
+// Golden Image Tests
+//
+
+use std::{env, path::Path};
+
+use super::{
+    api::Api,
+    application::{Application, Executor},
+    error::GamekitError,
+    graphics::GraphicsMode,
+    image::Image
+};
+
+defaults!();
+
+const UPDATE_VARIABLE: &str = "GAMEKIT_UPDATE_GOLDEN";
+
+// Runs an executor for a fixed number of fixed-timestep frames and compares
+// the final framebuffer against <reference_dir>/<name>.png. On mismatch the
+// actual and diff images are written to <output_dir>. Setting the environment
+// variable GAMEKIT_UPDATE_GOLDEN=1 (re-)writes the reference image instead.
+// Tests of other modes can share a reference through reference_name, e.g. a
+// headless GL run compared against the software rendering.
+pub struct GoldenTest {
+    pub name: String,
+    pub reference_name: String,
+    pub mode: GraphicsMode,
+    pub frames: u64,
+    pub delta_seconds: f32,
+    pub tolerance: u8,
+    pub reference_dir: String,
+    pub output_dir: String
+}
+
+impl GoldenTest {
+
+    pub fn new(name: &str, mode: GraphicsMode) -> GoldenTest {
+        return GoldenTest {
+            name: name.to_string(),
+            reference_name: name.to_string(),
+            mode,
+            frames: 60,
+            delta_seconds: 1.0 / 60.0,
+            tolerance: 2,
+            reference_dir: "tests/golden".to_string(),
+            output_dir: "target/golden".to_string()
+        };
+    }
+
+    pub fn reference_path(&self) -> String {
+        return format!("{}/{}.png", self.reference_dir, self.reference_name);
+    }
+
+    pub fn render<Exec: Executor>(&self) -> Result<Image, GamekitError> {
+        return self.render_with(Exec::new);
+    }
+
+    // creates the executor with factory instead of Executor::new
+    pub fn render_with<Exec: Executor, F>(&self, factory: F) -> Result<Image, GamekitError>
+        where F: FnOnce(&mut dyn Api) -> Result<Exec, GamekitError> {
+
+        let mut app = Application::<Exec>::with_factory(60, self.mode, factory)?;
+
+        if !app.run_frames(self.frames, self.delta_seconds) {
+            return Err(GamekitError::ImageMismatch(format!("{}: application stopped before frame {}", self.name, self.frames)));
+        }
+
+        return app.graphics().read_framebuffer();
+    }
+
+    pub fn run<Exec: Executor>(&self) -> Result<(), GamekitError> {
+        return self.run_with(Exec::new);
+    }
+
+    pub fn run_with<Exec: Executor, F>(&self, factory: F) -> Result<(), GamekitError>
+        where F: FnOnce(&mut dyn Api) -> Result<Exec, GamekitError> {
+
+        let actual = self.render_with(factory)?;
+
+        let reference_path = self.reference_path();
+        let actual_path = format!("{}/{}.actual.png", self.output_dir, self.name);
+        let diff_path = format!("{}/{}.diff.png", self.output_dir, self.name);
+
+        // shared references are only written by the test they are named after
+        if env::var(UPDATE_VARIABLE).map(|value| value == "1").unwrap_or(false) && self.reference_name == self.name {
+            info!("{}: updating reference image {}", self.name, reference_path);
+            return actual.save_png(&reference_path);
+        }
+
+        if !Path::new(&reference_path).exists() {
+            actual.save_png(&actual_path)?;
+            return Err(GamekitError::ImageMismatch(format!("{}: missing reference image {} (wrote {}, run with {}=1 to accept it)",
+                self.name, reference_path, actual_path, UPDATE_VARIABLE)));
+        }
+
+        let reference = Image::load_png(&reference_path)?;
+
+        let difference = match actual.compare(&reference, self.tolerance) {
+            Ok(difference) => difference,
+            Err(err) => {
+                actual.save_png(&actual_path)?;
+                return Err(GamekitError::ImageMismatch(format!("{}: {}", self.name, err)));
+            }
+        };
+
+        if difference.mismatched_pixels > 0 {
+            actual.save_png(&actual_path)?;
+            difference.diff_image.save_png(&diff_path)?;
+            return Err(GamekitError::ImageMismatch(format!("{}: {} pixels differ by more than {} (max {}), see {} and {}",
+                self.name, difference.mismatched_pixels, self.tolerance, difference.max_difference, actual_path, diff_path)));
+        }
+
+        debug!("{}: matches reference (max difference {})", self.name, difference.max_difference);
+
+        return Ok(());
+    }
+
+}
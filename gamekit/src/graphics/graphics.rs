@@ -6,12 +6,15 @@ extern crate sdl2;
 
 defaults!();
 
-use std::{ptr::null, os::raw::c_void, ffi::{CStr}};
+use std::{rc::Rc, cell::Cell, time::{SystemTime, UNIX_EPOCH}};
 
-use crate::graphics::primitives::{Primitives};
+use crate::graphics::{primitives::{Primitives}, headless::HeadlessContext, backend::{self, Backend}, opengl::OpenGlBackend, software::SoftwareBackend, image::Image, error::GamekitError};
 
 use super::gl;
 
+const SCREENSHOT_KEY: sdl2::keyboard::Keycode = sdl2::keyboard::Keycode::F12;
+const SCREENSHOT_DIRECTORY: &str = "screenshots";
+
 #[derive(Default)]
 
 pub struct Metrics {
@@ -34,6 +37,18 @@ impl Metrics {
     }
 }
 
+// window coordinates, origin top-left
+#[derive(Clone, Copy, Default)]
+pub struct Mouse {
+    pub x: f32,
+    pub y: f32,
+    pub left_button: bool,
+    pub click_x: f32,
+    pub click_y: f32,
+    // the left button went down during the current frame
+    pub clicked: bool
+}
+
 pub struct GraphicsContext {
     _gl: ()
 }
@@ -44,90 +59,85 @@ pub enum VSyncMode {
     Adaptive
 }
 
-pub struct Graphics {
+#[derive(Clone, Copy)]
+pub enum GraphicsMode {
+    Window,
+    Headless { width: u32, height: u32 },
+    Software { width: u32, height: u32 }
+}
+
+struct WindowSurface {
     _sdl: sdl2::Sdl,
     video_subsystem: sdl2::VideoSubsystem,
     window: sdl2::video::Window,
     _gl_context: sdl2::video::GLContext,
-    gl: (),
-    event_pump: sdl2::EventPump,
-    viewport_changed: bool,
-    layout_changed: bool,
-    pub metrics: Metrics
+    event_pump: sdl2::EventPump
 }
 
-extern "system"
-fn gl_debug_callback_handler(
-    _source: gl::types::GLenum,
-    _category: gl::types::GLenum,
-    id: gl::types::GLuint,
-    severity: gl::types::GLenum,
-    _length: gl::types::GLsizei,
-    message: *const gl::types::GLchar,
-    _user_param: *mut c_void
-) {
-    let msg =  unsafe { CStr::from_ptr(message) };
-    let s = msg.to_str().unwrap_or_default();
-
-    let t = "opengl";
-    let f = format!("[{}-{}] {}", t, id, s);
-
-    match severity {
-        gl::DEBUG_SEVERITY_HIGH => { error!(target: t, "{}", f)  },
-        gl::DEBUG_SEVERITY_MEDIUM => { warn!(target: t, "{}", f)  },
-        gl::DEBUG_SEVERITY_LOW => { info!(target: t, "{}", f)  },
-        _ =>  { debug!(target: t, "{}", f)  }
-    }
+enum Surface {
+    Window(WindowSurface),
+    Headless(HeadlessContext),
+    Offscreen { width: u32, height: u32 }
+}
 
-    //print!("[opengl] {}/{}/{}/{}", source, category, id, severity);
+pub struct Graphics {
+    surface: Surface,
+    backend: Rc<dyn Backend>,
+    gl: (),
+    viewport_changed: bool,
+    layout_changed: bool,
+    screenshot_requested: bool,
+    pub(crate) random_seed: Cell<i32>,
+    pub metrics: Metrics,
+    pub mouse: Mouse
 }
 
 impl Graphics {
 
-    pub fn new() -> Result<Graphics, String> {
+    pub fn new(mode: GraphicsMode) -> Result<Graphics, GamekitError> {
 
-        let sdl = sdl2::init().unwrap();
-        let video_subsystem = sdl.video().unwrap();
+        let surface = match mode {
+            GraphicsMode::Window => Surface::Window(create_window_surface()?),
+            GraphicsMode::Headless { width, height } => Surface::Headless(HeadlessContext::new(width, height)?),
+            GraphicsMode::Software { width, height } => {
+                return Ok(Self::with_backend(Rc::new(SoftwareBackend::new(width, height)), width, height));
+            }
+        };
 
-        let gl_attr = video_subsystem.gl_attr();
-        gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
-        gl_attr.set_context_version(4, 6);
+        let default_framebuffer = match &surface {
+            Surface::Headless(headless) => headless.framebuffer(),
+            _ => 0
+        };
 
-        let window = video_subsystem
-            .window("Window", 800, 600)
-            .opengl()
-            .resizable()
-            .build()
-            .unwrap();
+        let backend: Rc<dyn Backend> = Rc::new(OpenGlBackend::new(default_framebuffer));
 
-        let _gl_context = window.gl_create_context()?;
+        return Ok(Self::create(surface, backend));
+    }
 
-        let gl = gl::load_with(|symbol| video_subsystem.gl_get_proc_address(symbol) as *const std::os::raw::c_void);
+    // graphics without a device context, all rendering goes to the given backend
+    pub fn with_backend(backend: Rc<dyn Backend>, width: u32, height: u32) -> Graphics {
+        return Self::create(Surface::Offscreen { width, height }, backend);
+    }
 
-        unsafe {
-            gl::Enable(gl::DEBUG_OUTPUT);
-            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
-            gl::DebugMessageCallback(Some(gl_debug_callback_handler), null());
-            gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, null(), gl::TRUE);
-        }
+    fn create(surface: Surface, backend: Rc<dyn Backend>) -> Graphics {
 
-        let event_pump = sdl.event_pump()?;
+        backend::set_current(backend.clone());
 
         let mut engine = Graphics {
-            _sdl: sdl,
-            video_subsystem,
-            window,
-            _gl_context,
-            gl,
-            event_pump,
+            surface,
+            backend,
+            gl: (),
             viewport_changed: false,
             layout_changed: false,
-            metrics: Metrics::new()
+            screenshot_requested: false,
+            random_seed: Cell::new(1),
+            metrics: Metrics::new(),
+            mouse: Mouse::default()
         };
 
         engine.initialize();
 
-        return Ok(engine);
+        return engine;
     }
 
     pub fn free(&mut self) {
@@ -140,8 +150,21 @@ impl Graphics {
         }
     }
 
+    pub fn backend(&self) -> &dyn Backend {
+        return self.backend.as_ref();
+    }
+
+    pub fn is_headless(&self) -> bool {
+        return !matches!(self.surface, Surface::Window(_));
+    }
+
     pub fn is_minimized(&self) -> bool {
-        if (self.window.window_flags() & (sdl2::sys::SDL_WindowFlags::SDL_WINDOW_MINIMIZED as u32)) != 0 {
+        let window_surface = match &self.surface {
+            Surface::Window(window_surface) => window_surface,
+            _ => { return false; }
+        };
+
+        if (window_surface.window.window_flags() & (sdl2::sys::SDL_WindowFlags::SDL_WINDOW_MINIMIZED as u32)) != 0 {
             return true;
         }
 
@@ -155,13 +178,7 @@ impl Graphics {
         self.update_viewport();
         self.set_vsync_mode(VSyncMode::Adaptive);
 
-        unsafe {
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-            gl::Enable(gl::CULL_FACE);
-            gl::FrontFace(gl::CCW);
-            gl::CullFace(gl::BACK);
-        }
+        self.backend.initialize();
 
         Primitives::clear_color(0.0, 0.0, 0.0, 0.0);
 
@@ -179,7 +196,11 @@ impl Graphics {
             return;
         }
 
-        let ( w, h ) = self.window.drawable_size();
+        let ( w, h ) = match &self.surface {
+            Surface::Window(window_surface) => window_surface.window.drawable_size(),
+            Surface::Headless(headless) => headless.size(),
+            Surface::Offscreen { width, height } => (*width, *height)
+        };
 
         self.metrics.width = w;
         self.metrics.height = h;
@@ -195,12 +216,33 @@ impl Graphics {
 
     pub fn process_events(&mut self) -> bool {
 
-        let event_pump = &mut self.event_pump;
+        let event_pump = match &mut self.surface {
+            Surface::Window(window_surface) => &mut window_surface.event_pump,
+            _ => { return true; }
+        };
+
+        self.mouse.clicked = false;
 
         for event in event_pump.poll_iter() {
             match event {
                 sdl2::event::Event::Quit {..} => { return false },
                 sdl2::event::Event::KeyUp { keycode: Some(sdl2::keyboard::Keycode::Escape), .. } => { return false },
+                sdl2::event::Event::KeyUp { keycode: Some(SCREENSHOT_KEY), .. } => { self.screenshot_requested = true; },
+                sdl2::event::Event::MouseMotion { x, y, .. } => {
+                    self.mouse.x = x as f32;
+                    self.mouse.y = y as f32;
+                },
+                sdl2::event::Event::MouseButtonDown { mouse_btn: sdl2::mouse::MouseButton::Left, x, y, .. } => {
+                    self.mouse.x = x as f32;
+                    self.mouse.y = y as f32;
+                    self.mouse.left_button = true;
+                    self.mouse.click_x = x as f32;
+                    self.mouse.click_y = y as f32;
+                    self.mouse.clicked = true;
+                },
+                sdl2::event::Event::MouseButtonUp { mouse_btn: sdl2::mouse::MouseButton::Left, .. } => {
+                    self.mouse.left_button = false;
+                },
                 sdl2::event::Event::Window {timestamp: _, window_id: _, win_event} => {
                     match win_event {
                         sdl2::event::WindowEvent::Resized(..) => { self.viewport_changed = true; },
@@ -225,25 +267,66 @@ impl Graphics {
     }
 
     pub fn begin_draw(&mut self) -> bool {
+
+        if let Surface::Headless(headless) = &self.surface {
+            headless.bind();
+        }
+
         return true;
     }
 
     pub fn end_draw(&mut self) -> bool {
 
-        self.window.gl_swap_window();
+        if self.screenshot_requested {
+            // capture before the swap, the back buffer is undefined afterwards
+            self.screenshot_requested = false;
+            let file_path = screenshot_file_path();
+            match self.save_screenshot(&file_path) {
+                Ok(()) => { info!("Saved screenshot {}", file_path); },
+                Err(err) => { error!("Failed to save screenshot: {}", err); }
+            }
+        }
+
+        match &self.surface {
+            Surface::Window(window_surface) => { window_surface.window.gl_swap_window(); },
+            Surface::Headless(headless) => { headless.present(); },
+            Surface::Offscreen { .. } => {}
+        }
 
         return true;
     }
 
+    // reads the default framebuffer of the window or headless surface,
+    // see RenderTarget::read_image for render targets
+    pub fn read_framebuffer(&self) -> Result<Image, GamekitError> {
+        let width = self.metrics.width;
+        let height = self.metrics.height;
+        return Image::new(width, height, self.backend.read_framebuffer(0, 0, width, height));
+    }
+
+    pub fn save_screenshot(&self, file_path: &str) -> Result<(), GamekitError> {
+        return self.read_framebuffer()?.save_png(file_path);
+    }
+
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
     pub fn set_vsync_mode(&self, vsync_mode: VSyncMode) {
+
+        let video_subsystem = match &self.surface {
+            Surface::Window(window_surface) => &window_surface.video_subsystem,
+            _ => { return; }
+        };
+
         let _result = match vsync_mode {
-            VSyncMode::Synchronous => { self.video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::VSync) },
-            VSyncMode::Asynchronous => { self.video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::Immediate) },
+            VSyncMode::Synchronous => { video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::VSync) },
+            VSyncMode::Asynchronous => { video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::Immediate) },
             VSyncMode::Adaptive => {
-                let mut r = self.video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::LateSwapTearing);
+                let mut r = video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::LateSwapTearing);
                 if r.is_err() {
                     // adaptive mode is not supported, switch to synchronous mode
-                    r = self.video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::VSync);
+                    r = video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::VSync);
                 }
                 r
             }
@@ -251,7 +334,13 @@ impl Graphics {
     }
 
     pub fn get_vsync_mode(&self) -> VSyncMode {
-        let mode = self.video_subsystem.gl_get_swap_interval();
+
+        let video_subsystem = match &self.surface {
+            Surface::Window(window_surface) => &window_surface.video_subsystem,
+            _ => { return VSyncMode::Asynchronous; }
+        };
+
+        let mode = video_subsystem.gl_get_swap_interval();
         match mode {
             sdl2::video::SwapInterval::Immediate => VSyncMode::Asynchronous,
             sdl2::video::SwapInterval::VSync => VSyncMode::Synchronous,
@@ -261,6 +350,44 @@ impl Graphics {
 
 }
 
+fn screenshot_file_path() -> String {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    return format!("{}/screenshot_{}.png", SCREENSHOT_DIRECTORY, timestamp);
+}
+
+fn create_window_surface() -> Result<WindowSurface, GamekitError> {
+
+    let sdl = sdl2::init().map_err(GamekitError::window)?;
+    let video_subsystem = sdl.video().map_err(GamekitError::window)?;
+
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(4, 6);
+
+    let window = video_subsystem
+        .window("Window", 800, 600)
+        .opengl()
+        .resizable()
+        .build()
+        .map_err(GamekitError::window)?;
+
+    let _gl_context = window.gl_create_context().map_err(GamekitError::window)?;
+
+    gl::load_with(|symbol| video_subsystem.gl_get_proc_address(symbol) as *const std::os::raw::c_void);
+
+    let event_pump = sdl.event_pump().map_err(GamekitError::window)?;
+
+    let window_surface = WindowSurface {
+        _sdl: sdl,
+        video_subsystem,
+        window,
+        _gl_context,
+        event_pump
+    };
+
+    return Ok(window_surface);
+}
+
 impl Drop for Graphics {
     fn drop(&mut self) {
         debug!("drop engine");
@@ -0,0 +1,231 @@
+//
+// Headless
+//
+
+defaults!();
+
+use std::{ptr::null, ffi::CString};
+
+use super::{gl, egl, error::GamekitError};
+
+pub struct HeadlessContext {
+    display: egl::types::EGLDisplay,
+    context: egl::types::EGLContext,
+    framebuffer: u32,
+    color_buffer: u32,
+    depth_buffer: u32,
+    width: u32,
+    height: u32
+}
+
+impl HeadlessContext {
+
+    pub fn new(width: u32, height: u32) -> Result<HeadlessContext, GamekitError> {
+
+        egl::load()?;
+
+        let display = open_display()?;
+
+        let context = create_context(display)?;
+
+        let activated = unsafe { egl::MakeCurrent(display, egl::NO_SURFACE, egl::NO_SURFACE, context) };
+        if activated != egl::TRUE {
+            unsafe {
+                egl::DestroyContext(display, context);
+                egl::Terminate(display);
+            }
+            return Err(GamekitError::window("failed to activate headless context"));
+        }
+
+        gl::load_with(|symbol| {
+            let name = CString::new(symbol).unwrap();
+            unsafe { egl::GetProcAddress(name.as_ptr()) as *const std::os::raw::c_void }
+        });
+
+        let mut headless = HeadlessContext {
+            display,
+            context,
+            framebuffer: 0,
+            color_buffer: 0,
+            depth_buffer: 0,
+            width,
+            height
+        };
+
+        headless.create_framebuffer()?;
+
+        info!("headless context {}x{}", width, height);
+
+        return Ok(headless);
+    }
+
+    // checks that a headless context can be created without keeping one,
+    // the error tells why not (no libEGL, no display, no GL 4.5 driver)
+    pub fn is_available() -> Result<(), GamekitError> {
+
+        egl::load()?;
+
+        let display = open_display()?;
+        let context = create_context(display)?;
+
+        unsafe {
+            egl::DestroyContext(display, context);
+            egl::Terminate(display);
+        }
+
+        return Ok(());
+    }
+
+    fn create_framebuffer(&mut self) -> Result<(), GamekitError> {
+
+        let width = self.width as gl::types::GLsizei;
+        let height = self.height as gl::types::GLsizei;
+
+        let status = unsafe {
+
+            gl::GenFramebuffers(1, &mut self.framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+
+            gl::GenRenderbuffers(1, &mut self.color_buffer);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.color_buffer);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, width, height);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, self.color_buffer);
+
+            gl::GenRenderbuffers(1, &mut self.depth_buffer);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth_buffer);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, self.depth_buffer);
+
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::CheckFramebufferStatus(gl::FRAMEBUFFER)
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(GamekitError::gl("CheckFramebufferStatus", status));
+        }
+
+        return Ok(());
+    }
+
+    pub fn framebuffer(&self) -> u32 {
+        return self.framebuffer;
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        return (self.width, self.height);
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+        }
+    }
+
+    pub fn present(&self) {
+        unsafe {
+            gl::Finish();
+        }
+    }
+
+    pub fn free(&mut self) {
+
+        if self.context == egl::NO_CONTEXT {
+            return;
+        }
+
+        info!("Headless context free");
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteRenderbuffers(1, &self.color_buffer);
+            gl::DeleteRenderbuffers(1, &self.depth_buffer);
+
+            egl::MakeCurrent(self.display, egl::NO_SURFACE, egl::NO_SURFACE, egl::NO_CONTEXT);
+            egl::DestroyContext(self.display, self.context);
+            egl::Terminate(self.display);
+        }
+
+        self.framebuffer = 0;
+        self.color_buffer = 0;
+        self.depth_buffer = 0;
+        self.context = egl::NO_CONTEXT;
+    }
+
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        debug!("drop headless context");
+        self.free();
+    }
+}
+
+fn open_display() -> Result<egl::types::EGLDisplay, GamekitError> {
+
+    // prefer the surfaceless platform (no X11/Wayland, works with llvmpipe),
+    // fall back to the default display of the EGL vendor library
+
+    // eglGetPlatformDisplay is EGL 1.5
+    let mut display = egl::NO_DISPLAY;
+
+    if egl::GetPlatformDisplay::is_loaded() {
+        display = unsafe { egl::GetPlatformDisplay(egl::PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY as *mut _, null()) };
+    }
+
+    if display == egl::NO_DISPLAY {
+        display = unsafe { egl::GetDisplay(egl::DEFAULT_DISPLAY) };
+    }
+
+    if display == egl::NO_DISPLAY {
+        return Err(GamekitError::window("failed to open EGL display"));
+    }
+
+    let mut major: egl::types::EGLint = 0;
+    let mut minor: egl::types::EGLint = 0;
+
+    if unsafe { egl::Initialize(display, &mut major, &mut minor) } != egl::TRUE {
+        return Err(GamekitError::window(format!("failed to initialize EGL display (error 0x{:x})", unsafe { egl::GetError() })));
+    }
+
+    debug!("EGL {}.{}", major, minor);
+
+    if unsafe { egl::BindAPI(egl::OPENGL_API) } != egl::TRUE {
+        unsafe { egl::Terminate(display); }
+        return Err(GamekitError::window("EGL display does not support desktop OpenGL"));
+    }
+
+    return Ok(display);
+}
+
+fn create_context(display: egl::types::EGLDisplay) -> Result<egl::types::EGLContext, GamekitError> {
+
+    // GL 4.6 is preferred, software rasterizers such as llvmpipe may only expose 4.5
+
+    for minor_version in [6, 5] {
+
+        let attributes = [
+            egl::CONTEXT_MAJOR_VERSION as egl::types::EGLint, 4,
+            egl::CONTEXT_MINOR_VERSION as egl::types::EGLint, minor_version,
+            egl::CONTEXT_OPENGL_PROFILE_MASK as egl::types::EGLint, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as egl::types::EGLint,
+            egl::NONE as egl::types::EGLint
+        ];
+
+        // surfaceless + configless: the context only ever renders into framebuffer objects
+        let context = unsafe { egl::CreateContext(display, null(), egl::NO_CONTEXT, attributes.as_ptr()) };
+
+        if context != egl::NO_CONTEXT {
+            if minor_version < 6 {
+                warn!("headless context is limited to OpenGL 4.{}", minor_version);
+            }
+            return Ok(context);
+        }
+    }
+
+    let error = unsafe { egl::GetError() };
+
+    unsafe { egl::Terminate(display); }
+
+    return Err(GamekitError::window(format!("failed to create headless OpenGL context (error 0x{:x})", error)));
+}
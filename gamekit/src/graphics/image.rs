@@ -0,0 +1,185 @@
+//
+// Image
+//
+
+use std::{fs::File, io::BufWriter, path::Path};
+
+use super::error::GamekitError;
+
+extern crate png;
+
+defaults!();
+
+// RGBA8 pixels, rows from top to bottom
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>
+}
+
+pub struct ImageDifference {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    pub diff_image: Image
+}
+
+impl Image {
+
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Image, GamekitError> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(GamekitError::invalid_argument(format!("{}x{} image needs {} bytes, got {}", width, height, expected, pixels.len())));
+        }
+        return Ok(Image { width, height, pixels });
+    }
+
+    pub fn load_png(file_path: &str) -> Result<Image, GamekitError> {
+
+        let file = match File::open(Path::new(file_path)) {
+            Ok(file) => file,
+            Err(err) => { return Err(GamekitError::io(file_path, err)); }
+        };
+
+        let mut decoder = png::Decoder::new(file);
+        // palettes and low bit depths are expanded to 8 bits, 16 bit channels are stripped to 8 bits
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+        let mut reader = match decoder.read_info() {
+            Ok(reader) => reader,
+            Err(err) => { return Err(GamekitError::image_decode(file_path, err)); }
+        };
+
+        let mut buffer = vec![0u8; reader.output_buffer_size()];
+        let info = match reader.next_frame(&mut buffer) {
+            Ok(info) => info,
+            Err(err) => { return Err(GamekitError::image_decode(file_path, err)); }
+        };
+
+        if info.bit_depth != png::BitDepth::Eight {
+            return Err(GamekitError::image_decode(file_path, "unsupported bit depth"));
+        }
+
+        let pixel_count = info.width as usize * info.height as usize;
+        let mut pixels = Vec::with_capacity(pixel_count * 4);
+
+        for y in 0..info.height as usize {
+            let line = &buffer[y * info.line_size..(y + 1) * info.line_size];
+            match info.color_type {
+                png::ColorType::Rgba => pixels.extend_from_slice(&line[..info.width as usize * 4]),
+                png::ColorType::Rgb => line.chunks_exact(3).take(info.width as usize).for_each(|p| pixels.extend_from_slice(&[p[0], p[1], p[2], 255])),
+                png::ColorType::GrayscaleAlpha => line.chunks_exact(2).take(info.width as usize).for_each(|p| pixels.extend_from_slice(&[p[0], p[0], p[0], p[1]])),
+                png::ColorType::Grayscale => line.iter().take(info.width as usize).for_each(|p| pixels.extend_from_slice(&[*p, *p, *p, 255])),
+                png::ColorType::Indexed => { return Err(GamekitError::image_decode(file_path, "unexpanded palette")); }
+            }
+        }
+
+        return Image::new(info.width, info.height, pixels);
+    }
+
+    pub fn save_png(&self, file_path: &str) -> Result<(), GamekitError> {
+
+        let path = Path::new(file_path);
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let file = match File::create(path) {
+            Ok(file) => file,
+            Err(err) => { return Err(GamekitError::io(file_path, err)); }
+        };
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let result = encoder.write_header().and_then(|mut writer| writer.write_image_data(&self.pixels));
+        if let Err(err) = result {
+            return Err(GamekitError::image_encode(file_path, err));
+        }
+
+        return Ok(());
+    }
+
+    // per-channel comparison, pixels differing by more than the tolerance are marked red in the diff image
+    pub fn compare(&self, other: &Image, tolerance: u8) -> Result<ImageDifference, GamekitError> {
+
+        if self.width != other.width || self.height != other.height {
+            return Err(GamekitError::ImageMismatch(format!("image size mismatch: {}x{} vs {}x{}", self.width, self.height, other.width, other.height)));
+        }
+
+        let mut mismatched_pixels = 0;
+        let mut max_difference = 0u8;
+        let mut diff_pixels = Vec::with_capacity(self.pixels.len());
+
+        for (a, b) in self.pixels.chunks_exact(4).zip(other.pixels.chunks_exact(4)) {
+
+            let difference = (0..4).map(|i| a[i].abs_diff(b[i])).max().unwrap_or(0);
+            max_difference = max_difference.max(difference);
+
+            if difference > tolerance {
+                mismatched_pixels += 1;
+                diff_pixels.extend_from_slice(&[255, 0, 0, 255]);
+            } else {
+                let luminance = ((a[0] as u32 + a[1] as u32 + a[2] as u32) / 12) as u8;
+                diff_pixels.extend_from_slice(&[luminance, luminance, luminance, 255]);
+            }
+        }
+
+        let difference = ImageDifference {
+            mismatched_pixels,
+            max_difference,
+            diff_image: Image::new(self.width, self.height, diff_pixels)?
+        };
+
+        return Ok(difference);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::File, io::BufWriter, process};
+    use super::Image;
+
+    fn write_png(test: &str, width: u32, height: u32, color: png::ColorType, depth: png::BitDepth, palette: Option<Vec<u8>>, data: &[u8]) -> String {
+        let path = env::temp_dir().join(format!("gamekit_image_{}_{}.png", test, process::id()));
+        let file = File::create(&path).unwrap();
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        if let Some(palette) = palette {
+            encoder.set_palette(palette);
+        }
+        encoder.write_header().unwrap().write_image_data(data).unwrap();
+        return path.to_str().unwrap().to_string();
+    }
+
+    #[test]
+    fn low_bit_depth_grayscale_is_expanded() {
+        // 2 bits per pixel, one byte per row
+        let path = write_png("gray2", 3, 2, png::ColorType::Grayscale, png::BitDepth::Two, None, &[0b00_01_11_00, 0b11_10_00_00]);
+        let image = Image::load_png(&path).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.pixels, vec![
+            0, 0, 0, 255,  85, 85, 85, 255,  255, 255, 255, 255,
+            255, 255, 255, 255,  170, 170, 170, 255,  0, 0, 0, 255
+        ]);
+    }
+
+    #[test]
+    fn palette_is_expanded() {
+        let path = write_png("indexed1", 2, 1, png::ColorType::Indexed, png::BitDepth::One, Some(vec![10, 20, 30, 40, 50, 60]), &[0b01_000000]);
+        let image = Image::load_png(&path).unwrap();
+        assert_eq!(image.pixels, vec![10, 20, 30, 255, 40, 50, 60, 255]);
+    }
+
+    #[test]
+    fn sixteen_bit_channels_are_stripped() {
+        let path = write_png("rgb16", 1, 1, png::ColorType::Rgb, png::BitDepth::Sixteen, None, &[0x12, 0x34, 0x80, 0x00, 0xff, 0xff]);
+        let image = Image::load_png(&path).unwrap();
+        assert_eq!(image.pixels, vec![0x12, 0x80, 0xff, 255]);
+    }
+
+}
@@ -0,0 +1,414 @@
+//
+// Buffer Block Layout
+//
+
+// std140 (uniform blocks) and std430 (shader storage blocks) memory layouts,
+// OpenGL 4.6 section 7.6.2.2. Values are written member by member with the
+// padding the layout requires, so CPU structs need no manual padding:
+//
+//     #[derive(BlockLayout)]
+//     struct Light {
+//         position: Vector3,
+//         intensity: f32,
+//         color: Vector4
+//     }
+//
+// Rust arrays are GLSL arrays ([f32; 4] is float[4], not vec4), use the vector
+// and matrix types from graphics::types for GLSL vectors and matrices.
+
+use super::types::{Vector2, Vector2i, Vector3, Vector4, Matrix2, Matrix3, Matrix4};
+
+pub use gamekit_derive::BlockLayout;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Std140,
+    Std430
+}
+
+impl Layout {
+    pub const fn of<T: BlockLayout>(self) -> TypeLayout {
+        return match self {
+            Layout::Std140 => T::STD140,
+            Layout::Std430 => T::STD430
+        };
+    }
+}
+
+// base alignment and size in bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeLayout {
+    pub alignment: usize,
+    pub size: usize
+}
+
+pub trait BlockLayout {
+
+    const STD140: TypeLayout;
+    const STD430: TypeLayout;
+
+    // writes the value to the start of out, which holds at least the size of the layout
+    fn write(&self, layout: Layout, out: &mut [u8]);
+
+    // names and offsets of the members the way programs report them, nested
+    // structs as "inner.member" and arrays by their first element, "values[0]"
+    fn members(_layout: Layout) -> Vec<(String, usize)> {
+        return Vec::new();
+    }
+
+}
+
+// name of a struct member and the members of its type
+pub fn nested_members<T: BlockLayout>(layout: Layout, name: &str, offset: usize) -> Vec<(String, usize)> {
+
+    let members = T::members(layout);
+    if members.is_empty() {
+        return vec![(name.to_string(), offset)];
+    }
+
+    return members.into_iter().map(|(member, member_offset)| {
+        let separator = if member.starts_with('[') { "" } else { "." };
+        (format!("{}{}{}", name, separator, member), offset + member_offset)
+    }).collect();
+}
+
+// value in a zero padded buffer of the layout size
+pub fn to_bytes<T: BlockLayout>(value: &T, layout: Layout) -> Vec<u8> {
+    let mut data = vec![0u8; layout.of::<T>().size];
+    value.write(layout, &mut data);
+    return data;
+}
+
+pub const fn round_up(value: usize, alignment: usize) -> usize {
+    return value.div_ceil(alignment) * alignment;
+}
+
+// offset of a struct member given the layouts of all members
+pub const fn member_offset(layout: Layout, members: &[TypeLayout], index: usize) -> usize {
+    let _ = layout;
+    let mut offset = 0;
+    let mut member = 0;
+    while member < index {
+        offset = round_up(offset, members[member].alignment) + members[member].size;
+        member += 1;
+    }
+    return round_up(offset, members[index].alignment);
+}
+
+// structs are aligned to their largest member, rounded up to vec4 in std140
+pub const fn struct_layout(layout: Layout, members: &[TypeLayout]) -> TypeLayout {
+
+    let mut alignment = 1;
+    let mut end = 0;
+    let mut member = 0;
+
+    while member < members.len() {
+        if members[member].alignment > alignment {
+            alignment = members[member].alignment;
+        }
+        end = round_up(end, members[member].alignment) + members[member].size;
+        member += 1;
+    }
+
+    if let Layout::Std140 = layout {
+        alignment = round_up(alignment, 16);
+    }
+
+    return TypeLayout { alignment, size: round_up(end, alignment) };
+}
+
+// checks derived layouts, base alignments are powers of two and members
+// start at a multiple of theirs
+pub const fn check_struct_layout(layout: Layout, members: &[TypeLayout]) -> bool {
+    let mut member = 0;
+    while member < members.len() {
+        if !members[member].alignment.is_power_of_two() || member_offset(layout, members, member) % members[member].alignment != 0 {
+            return false;
+        }
+        member += 1;
+    }
+    return true;
+}
+
+// arrays of T, elements are rounded up to vec4 alignment in std140
+const fn array_layout(element: TypeLayout, length: usize, layout: Layout) -> TypeLayout {
+    let alignment = match layout {
+        Layout::Std140 => round_up(element.alignment, 16),
+        Layout::Std430 => element.alignment
+    };
+    return TypeLayout { alignment, size: round_up(element.size, alignment) * length };
+}
+
+const fn array_stride(element: TypeLayout, layout: Layout) -> usize {
+    return array_layout(element, 1, layout).size;
+}
+
+const SCALAR: TypeLayout = TypeLayout { alignment: 4, size: 4 };
+const VEC2: TypeLayout = TypeLayout { alignment: 8, size: 8 };
+const VEC3: TypeLayout = TypeLayout { alignment: 16, size: 12 };
+const VEC4: TypeLayout = TypeLayout { alignment: 16, size: 16 };
+
+fn write_words(words: &[[u8; 4]], out: &mut [u8]) {
+    for (index, word) in words.iter().enumerate() {
+        out[index * 4..index * 4 + 4].copy_from_slice(word);
+    }
+}
+
+impl BlockLayout for f32 {
+    const STD140: TypeLayout = SCALAR;
+    const STD430: TypeLayout = SCALAR;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        write_words(&[self.to_ne_bytes()], out);
+    }
+}
+
+impl BlockLayout for i32 {
+    const STD140: TypeLayout = SCALAR;
+    const STD430: TypeLayout = SCALAR;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        write_words(&[self.to_ne_bytes()], out);
+    }
+}
+
+impl BlockLayout for u32 {
+    const STD140: TypeLayout = SCALAR;
+    const STD430: TypeLayout = SCALAR;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        write_words(&[self.to_ne_bytes()], out);
+    }
+}
+
+// GLSL bools are 32 bit
+impl BlockLayout for bool {
+    const STD140: TypeLayout = SCALAR;
+    const STD430: TypeLayout = SCALAR;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        write_words(&[(*self as u32).to_ne_bytes()], out);
+    }
+}
+
+impl BlockLayout for Vector2 {
+    const STD140: TypeLayout = VEC2;
+    const STD430: TypeLayout = VEC2;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        let (x, y) = (self.x, self.y);
+        write_words(&[x.to_ne_bytes(), y.to_ne_bytes()], out);
+    }
+}
+
+impl BlockLayout for Vector2i {
+    const STD140: TypeLayout = VEC2;
+    const STD430: TypeLayout = VEC2;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        let (x, y) = (self.x, self.y);
+        write_words(&[x.to_ne_bytes(), y.to_ne_bytes()], out);
+    }
+}
+
+impl BlockLayout for Vector3 {
+    const STD140: TypeLayout = VEC3;
+    const STD430: TypeLayout = VEC3;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        let (x, y, z) = (self.x, self.y, self.z);
+        write_words(&[x.to_ne_bytes(), y.to_ne_bytes(), z.to_ne_bytes()], out);
+    }
+}
+
+impl BlockLayout for Vector4 {
+    const STD140: TypeLayout = VEC4;
+    const STD430: TypeLayout = VEC4;
+    fn write(&self, _layout: Layout, out: &mut [u8]) {
+        let (x, y, z, w) = (self.x, self.y, self.z, self.w);
+        write_words(&[x.to_ne_bytes(), y.to_ne_bytes(), z.to_ne_bytes(), w.to_ne_bytes()], out);
+    }
+}
+
+// column major matrices are laid out like arrays of their column vectors
+
+impl BlockLayout for Matrix2 {
+    const STD140: TypeLayout = array_layout(VEC2, 2, Layout::Std140);
+    const STD430: TypeLayout = array_layout(VEC2, 2, Layout::Std430);
+    fn write(&self, layout: Layout, out: &mut [u8]) {
+        let stride = array_stride(VEC2, layout);
+        for (index, column) in self.columns.iter().enumerate() {
+            Vector2::new(column[0], column[1]).write(layout, &mut out[index * stride..]);
+        }
+    }
+}
+
+impl BlockLayout for Matrix3 {
+    const STD140: TypeLayout = array_layout(VEC3, 3, Layout::Std140);
+    const STD430: TypeLayout = array_layout(VEC3, 3, Layout::Std430);
+    fn write(&self, layout: Layout, out: &mut [u8]) {
+        let stride = array_stride(VEC3, layout);
+        for (index, column) in self.columns.iter().enumerate() {
+            Vector3::new(column[0], column[1], column[2]).write(layout, &mut out[index * stride..]);
+        }
+    }
+}
+
+impl BlockLayout for Matrix4 {
+    const STD140: TypeLayout = array_layout(VEC4, 4, Layout::Std140);
+    const STD430: TypeLayout = array_layout(VEC4, 4, Layout::Std430);
+    fn write(&self, layout: Layout, out: &mut [u8]) {
+        let stride = array_stride(VEC4, layout);
+        for (index, column) in self.columns.iter().enumerate() {
+            Vector4::new(column[0], column[1], column[2], column[3]).write(layout, &mut out[index * stride..]);
+        }
+    }
+}
+
+impl<T: BlockLayout, const N: usize> BlockLayout for [T; N] {
+    const STD140: TypeLayout = array_layout(T::STD140, N, Layout::Std140);
+    const STD430: TypeLayout = array_layout(T::STD430, N, Layout::Std430);
+    fn write(&self, layout: Layout, out: &mut [u8]) {
+        let stride = array_stride(layout.of::<T>(), layout);
+        for (index, element) in self.iter().enumerate() {
+            element.write(layout, &mut out[index * stride..]);
+        }
+    }
+    fn members(layout: Layout) -> Vec<(String, usize)> {
+        return nested_members::<T>(layout, "[0]", 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockLayout, Layout, TypeLayout, to_bytes, check_struct_layout};
+    use crate::graphics::types::{Vector2, Vector3, Vector4, Matrix2, Matrix3};
+
+    // offsets and sizes below follow the rules of OpenGL 4.6 section 7.6.2.2
+
+    #[derive(Clone, Copy, BlockLayout)]
+    struct Light {
+        position: Vector3,
+        intensity: f32,
+        color: Vector4
+    }
+
+    #[derive(Clone, Copy, BlockLayout)]
+    struct Weights {
+        scale: f32,
+        values: [f32; 3],
+        offset: Vector2
+    }
+
+    #[derive(Clone, Copy, BlockLayout)]
+    struct Transform {
+        a: f32,
+        rotation: Matrix3,
+        b: f32
+    }
+
+    #[derive(Clone, Copy, BlockLayout)]
+    struct Pair {
+        x: f32,
+        y: f32
+    }
+
+    #[derive(Clone, Copy, BlockLayout)]
+    struct Nested {
+        a: f32,
+        pair: Pair,
+        pairs: [Pair; 2]
+    }
+
+    fn members<T: BlockLayout>(layout: Layout) -> Vec<(String, usize)> {
+        return T::members(layout);
+    }
+
+    fn named(members: &[(&str, usize)]) -> Vec<(String, usize)> {
+        return members.iter().map(|(name, offset)| (name.to_string(), *offset)).collect();
+    }
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        return bytes.chunks_exact(4).map(|word| f32::from_ne_bytes([word[0], word[1], word[2], word[3]])).collect();
+    }
+
+    #[test]
+    fn scalar_follows_vec3() {
+        let expected = named(&[("position", 0), ("intensity", 12), ("color", 16)]);
+        assert_eq!(members::<Light>(Layout::Std140), expected);
+        assert_eq!(members::<Light>(Layout::Std430), expected);
+        assert_eq!(Light::STD140, TypeLayout { alignment: 16, size: 32 });
+        assert_eq!(Light::STD430, TypeLayout { alignment: 16, size: 32 });
+
+        let light = Light { position: Vector3::new(1.0, 2.0, 3.0), intensity: 4.0, color: Vector4::new(5.0, 6.0, 7.0, 8.0) };
+        assert_eq!(floats(&to_bytes(&light, Layout::Std140)), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    fn std140_arrays_have_a_stride_of_16() {
+        assert_eq!(members::<Weights>(Layout::Std140), named(&[("scale", 0), ("values[0]", 16), ("offset", 64)]));
+        assert_eq!(Weights::STD140, TypeLayout { alignment: 16, size: 80 });
+
+        assert_eq!(members::<Weights>(Layout::Std430), named(&[("scale", 0), ("values[0]", 4), ("offset", 16)]));
+        assert_eq!(Weights::STD430, TypeLayout { alignment: 8, size: 24 });
+
+        let weights = Weights { scale: 1.0, values: [2.0, 3.0, 4.0], offset: Vector2::new(5.0, 6.0) };
+        assert_eq!(floats(&to_bytes(&weights, Layout::Std140)), vec![
+            1.0, 0.0, 0.0, 0.0,
+            2.0, 0.0, 0.0, 0.0,
+            3.0, 0.0, 0.0, 0.0,
+            4.0, 0.0, 0.0, 0.0,
+            5.0, 6.0, 0.0, 0.0
+        ]);
+        assert_eq!(floats(&to_bytes(&weights, Layout::Std430)), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn matrix_columns_are_vectors() {
+        assert_eq!(Matrix2::STD140, TypeLayout { alignment: 16, size: 32 });
+        assert_eq!(Matrix2::STD430, TypeLayout { alignment: 8, size: 16 });
+        assert_eq!(Matrix3::STD140, TypeLayout { alignment: 16, size: 48 });
+        assert_eq!(Matrix3::STD430, TypeLayout { alignment: 16, size: 48 });
+
+        let expected = named(&[("a", 0), ("rotation", 16), ("b", 64)]);
+        assert_eq!(members::<Transform>(Layout::Std140), expected);
+        assert_eq!(members::<Transform>(Layout::Std430), expected);
+        assert_eq!(Transform::STD140, TypeLayout { alignment: 16, size: 80 });
+
+        let transform = Transform { a: 1.0, rotation: Matrix3::new([[2.0, 3.0, 4.0], [5.0, 6.0, 7.0], [8.0, 9.0, 10.0]]), b: 11.0 };
+        assert_eq!(floats(&to_bytes(&transform, Layout::Std430)), vec![
+            1.0, 0.0, 0.0, 0.0,
+            2.0, 3.0, 4.0, 0.0,
+            5.0, 6.0, 7.0, 0.0,
+            8.0, 9.0, 10.0, 0.0,
+            11.0, 0.0, 0.0, 0.0
+        ]);
+
+        let matrix = Matrix2::new([[1.0, 2.0], [3.0, 4.0]]);
+        assert_eq!(floats(&to_bytes(&matrix, Layout::Std140)), vec![1.0, 2.0, 0.0, 0.0, 3.0, 4.0, 0.0, 0.0]);
+        assert_eq!(floats(&to_bytes(&matrix, Layout::Std430)), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn nested_structs_are_aligned_and_named() {
+        assert_eq!(Pair::STD140, TypeLayout { alignment: 16, size: 16 });
+        assert_eq!(Pair::STD430, TypeLayout { alignment: 4, size: 8 });
+
+        assert_eq!(members::<Nested>(Layout::Std140), named(&[("a", 0), ("pair.x", 16), ("pair.y", 20), ("pairs[0].x", 32), ("pairs[0].y", 36)]));
+        assert_eq!(Nested::STD140, TypeLayout { alignment: 16, size: 64 });
+
+        assert_eq!(members::<Nested>(Layout::Std430), named(&[("a", 0), ("pair.x", 4), ("pair.y", 8), ("pairs[0].x", 12), ("pairs[0].y", 16)]));
+        assert_eq!(Nested::STD430, TypeLayout { alignment: 4, size: 28 });
+
+        let nested = Nested { a: 1.0, pair: Pair { x: 2.0, y: 3.0 }, pairs: [Pair { x: 4.0, y: 5.0 }, Pair { x: 6.0, y: 7.0 }] };
+        assert_eq!(floats(&to_bytes(&nested, Layout::Std140)), vec![
+            1.0, 0.0, 0.0, 0.0,
+            2.0, 3.0, 0.0, 0.0,
+            4.0, 5.0, 0.0, 0.0,
+            6.0, 7.0, 0.0, 0.0
+        ]);
+        assert_eq!(floats(&to_bytes(&nested, Layout::Std430)), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn misaligned_members_are_rejected() {
+        let scalar = TypeLayout { alignment: 4, size: 4 };
+        assert!(check_struct_layout(Layout::Std430, &[scalar, Vector4::STD430]));
+        assert!(!check_struct_layout(Layout::Std430, &[scalar, TypeLayout { alignment: 12, size: 12 }]));
+        assert!(!check_struct_layout(Layout::Std430, &[TypeLayout { alignment: 2, size: 3 }, TypeLayout { alignment: 3, size: 3 }]));
+    }
+
+}
@@ -3,16 +3,34 @@
 //
 
 pub mod gl;
+pub mod egl;
 pub mod application;
+pub mod error;
 pub mod types;
-pub mod std140;
+pub mod layout;
+pub mod vertex_layout;
 pub mod buffers;
 pub mod api;
+pub mod backend;
+pub mod opengl;
+pub mod recording;
+pub mod software;
 pub mod primitives;
 pub mod texture;
+pub mod render_target;
+pub mod postprocess;
+pub mod shadertoy;
 pub mod shader;
+pub mod shader_compiler;
+pub mod preprocessor;
 pub mod program;
+pub mod program_cache;
+pub mod reflection;
+pub mod shader_watcher;
 pub mod uniform;
 pub mod graphics;
+pub mod headless;
 pub mod sprite;
 pub mod sprite_batch;
+pub mod image;
+pub mod golden;
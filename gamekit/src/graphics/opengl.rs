@@ -0,0 +1,1209 @@
+//
+// OpenGL 4.6 Backend
+//
+
+defaults!();
+
+use std::{ptr::null, os::raw::c_void, ffi::{CStr, CString}, cell::Cell};
+
+use super::{
+    gl,
+    api::{BlendMode, MemoryBarrier},
+    primitives::DrawMode,
+    buffers::{BufferType, BufferUsage},
+    error::GamekitError,
+    reflection::{AttributeInfo, BlockInfo, BlockMember, DataType, ProgramInterface, UniformInfo},
+    shader::ShaderType,
+    backend::{Backend, TextureFormat, DepthStencilFormat, ShaderCode, VertexAttribute, AttributeType, UniformValue}
+};
+
+const PIXEL_ALIGNMENT: i32 = 1;
+const FENCE_WAIT_NANOSECONDS: u64 = 1_000_000;
+
+pub struct OpenGlBackend {
+    default_framebuffer: u32,
+    current_framebuffer: Cell<u32>,
+    spirv_supported: bool
+}
+
+extern "system"
+fn gl_debug_callback_handler(
+    _source: gl::types::GLenum,
+    _category: gl::types::GLenum,
+    id: gl::types::GLuint,
+    severity: gl::types::GLenum,
+    _length: gl::types::GLsizei,
+    message: *const gl::types::GLchar,
+    _user_param: *mut c_void
+) {
+    let msg =  unsafe { CStr::from_ptr(message) };
+    let s = msg.to_str().unwrap_or_default();
+
+    let t = "opengl";
+    let f = format!("[{}-{}] {}", t, id, s);
+
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => { error!(target: t, "{}", f)  },
+        gl::DEBUG_SEVERITY_MEDIUM => { warn!(target: t, "{}", f)  },
+        gl::DEBUG_SEVERITY_LOW => { info!(target: t, "{}", f)  },
+        _ =>  { debug!(target: t, "{}", f)  }
+    }
+
+    //print!("[opengl] {}/{}/{}/{}", source, category, id, severity);
+}
+
+impl OpenGlBackend {
+
+    // expects the function pointers to be loaded for the current context,
+    // the default framebuffer is 0 for windows and the offscreen FBO when headless
+    pub fn new(default_framebuffer: u32) -> OpenGlBackend {
+
+        unsafe {
+            gl::Enable(gl::DEBUG_OUTPUT);
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            gl::DebugMessageCallback(Some(gl_debug_callback_handler), null());
+            gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, null(), gl::TRUE);
+        }
+
+        // SPIR-V ingestion is core since 4.6, GL_ARB_gl_spirv before
+        let spirv_supported = gl_version() >= (4, 6) || has_extension("GL_ARB_gl_spirv");
+        if !spirv_supported {
+            info!("SPIR-V shaders not supported, using GLSL sources");
+        }
+
+        return OpenGlBackend {
+            default_framebuffer,
+            current_framebuffer: Cell::new(default_framebuffer),
+            spirv_supported
+        };
+    }
+
+}
+
+impl Backend for OpenGlBackend {
+
+    fn initialize(&self) {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Enable(gl::CULL_FACE);
+            gl::FrontFace(gl::CCW);
+            gl::CullFace(gl::BACK);
+        }
+    }
+
+    fn viewport(&self, x: u32, y: u32, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(x as gl::types::GLsizei,
+                         y as gl::types::GLsizei,
+                         width as gl::types::GLsizei,
+                         height as gl::types::GLsizei);
+        }
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
+        unsafe {
+            gl::ClearColor(red, green, blue, alpha);
+        }
+    }
+
+    fn clear(&self) {
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    fn set_blend_mode(&self, blend_mode: BlendMode) {
+
+        let src;
+        let dst;
+
+        match blend_mode {
+            BlendMode::Normal => {
+                src = gl::SRC_ALPHA;
+                dst = gl::ONE_MINUS_SRC_ALPHA;
+            },
+            BlendMode::Additive => {
+                src = gl::SRC_ALPHA;
+                dst = gl::ONE;
+            },
+            BlendMode::Multiply => {
+                src = gl::DST_COLOR;
+                dst = gl::ZERO;
+            },
+            BlendMode::Opaque => {
+                src = gl::ONE;
+                dst = gl::ZERO;
+            }
+        }
+
+        unsafe {
+            gl::BlendFunc(src, dst);
+        }
+    }
+
+    fn read_pixels(&self, x: u32, y: u32, width: u32, height: u32, framebuffer_height: u32) -> Vec<u8> {
+
+        let bytes_per_line = width as usize * 4;
+        let mut pixels = vec![0u8; bytes_per_line * height as usize];
+
+        // window coordinates start at the bottom, x/y are given from the top.
+        // The viewport may only cover part of the framebuffer, so the rows are
+        // flipped against the framebuffer height.
+
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, PIXEL_ALIGNMENT);
+            gl::ReadPixels(
+                x as gl::types::GLint,
+                framebuffer_height as gl::types::GLint - (y + height) as gl::types::GLint,
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut c_void
+            );
+        }
+
+        return flip_rows(&pixels, bytes_per_line);
+    }
+
+    fn draw_arrays(&self, mode: DrawMode, ofs: usize, count: usize) {
+        unsafe {
+            gl::DrawArrays(
+                map_draw_mode(&mode),
+                ofs as gl::types::GLint,     // starting index in the enabled arrays
+                count as gl::types::GLsizei         // number of indices to be rendered
+            );
+        }
+    }
+
+    fn draw_elements(&self, mode: DrawMode, count: usize) {
+        unsafe {
+            gl::DrawElements(
+                map_draw_mode(&mode),
+                count as gl::types::GLsizei,
+                gl::UNSIGNED_INT,
+                0 as *const _
+            );
+        }
+    }
+
+    fn draw_elements_instanced(&self, mode: DrawMode, count: usize, num_instances: usize) {
+        unsafe {
+            gl::DrawElementsInstanced(
+                map_draw_mode(&mode),
+                count as gl::types::GLsizei,
+                gl::UNSIGNED_INT,
+                0 as *const _,
+                num_instances as gl::types::GLsizei
+            );
+        }
+    }
+
+    fn dispatch_compute(&self, x: u32, y: u32, z: u32) {
+        unsafe {
+            gl::DispatchCompute(x, y, z);
+        }
+    }
+
+    fn dispatch_compute_indirect(&self, offset: usize) {
+        unsafe {
+            gl::DispatchComputeIndirect(offset as gl::types::GLintptr);
+        }
+    }
+
+    fn memory_barrier(&self, barriers: &[MemoryBarrier]) {
+        let bits = barriers.iter().fold(0, |bits, barrier| bits | map_barrier(barrier));
+        if bits != 0 {
+            unsafe {
+                gl::MemoryBarrier(bits);
+            }
+        }
+    }
+
+    fn create_buffer(&self) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe { gl::CreateBuffers(1, &mut id); };
+        return id as u32;
+    }
+
+    fn delete_buffer(&self, id: u32) {
+        if id != 0 {
+            unsafe {
+                gl::DeleteBuffers(1, &id);
+            }
+        }
+    }
+
+    fn bind_buffer(&self, id: u32, buffer_type: &BufferType) {
+        unsafe {
+            gl::BindBuffer(map_type(buffer_type), id);
+        }
+    }
+
+    fn bind_buffer_base(&self, id: u32, binding_point: u32, buffer_type: &BufferType) {
+        unsafe {
+            gl::BindBufferBase(map_type(buffer_type), binding_point, id);
+        }
+    }
+
+    fn unbind_buffer(&self, _id: u32, buffer_type: &BufferType) {
+        unsafe {
+            gl::BindBuffer(map_type(buffer_type), 0);
+        }
+    }
+
+    fn buffer_data(&self, id: u32, buffer_usage: &BufferUsage, data: &[u8]) {
+        unsafe {
+            gl::NamedBufferData(
+                id,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid,
+                map_usage(buffer_usage)
+            );
+        }
+    }
+
+    fn buffer_sub_data(&self, id: u32, offset: usize, data: &[u8]) {
+        unsafe {
+            gl::NamedBufferSubData(
+                id,
+                offset as gl::types::GLintptr,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_ptr() as *const gl::types::GLvoid
+            );
+        }
+    }
+
+    fn create_mapped_buffer(&self, size: usize) -> Result<(u32, *mut u8), GamekitError> {
+
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        let zeros = vec![0u8; size];
+
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::CreateBuffers(1, &mut id);
+            gl::NamedBufferStorage(id, size as gl::types::GLsizeiptr, zeros.as_ptr() as *const c_void, flags);
+
+            let data = gl::MapNamedBufferRange(id, 0, size as gl::types::GLsizeiptr, flags);
+            if data.is_null() {
+                let result = gl::GetError();
+                gl::DeleteBuffers(1, &id);
+                return Err(GamekitError::gl("MapNamedBufferRange", result));
+            }
+
+            return Ok((id, data as *mut u8));
+        }
+    }
+
+    fn fence_sync(&self) -> u64 {
+        let fence = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        return fence as usize as u64;
+    }
+
+    fn wait_fence(&self, fence: u64) {
+        if fence == 0 {
+            return;
+        }
+
+        // the first wait flushes the commands so the fence can be signaled
+        let mut flags = gl::SYNC_FLUSH_COMMANDS_BIT;
+        loop {
+            let result = unsafe { gl::ClientWaitSync(fence as usize as gl::types::GLsync, flags, FENCE_WAIT_NANOSECONDS) };
+            match result {
+                gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => { return; },
+                gl::TIMEOUT_EXPIRED => { flags = 0; },
+                _ => {
+                    warn!("failed to wait for fence");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn delete_fence(&self, fence: u64) {
+        if fence != 0 {
+            unsafe { gl::DeleteSync(fence as usize as gl::types::GLsync); }
+        }
+    }
+
+    fn create_vertex_array(&self) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::CreateVertexArrays(1, &mut id);
+        }
+        return id;
+    }
+
+    fn delete_vertex_array(&self, id: u32) {
+        if id != 0 {
+            unsafe {
+                gl::DeleteVertexArrays(1, &id);
+            }
+        }
+    }
+
+    fn bind_vertex_array(&self, id: u32) {
+        unsafe {
+            gl::BindVertexArray(id);
+        }
+    }
+
+    fn unbind_vertex_array(&self) {
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+    }
+
+    fn vertex_array_attribute(&self, vertex_array: u32, binding: u32, attribute: &VertexAttribute) {
+
+        let location = attribute.location;
+        let components = attribute.components as gl::types::GLint;
+        let offset = attribute.offset as gl::types::GLuint;
+
+        unsafe {
+            match attribute.data_type {
+                AttributeType::Float => gl::VertexArrayAttribFormat(vertex_array, location, components, gl::FLOAT, gl::FALSE, offset),
+                AttributeType::NormalizedByte => gl::VertexArrayAttribFormat(vertex_array, location, components, gl::BYTE, gl::TRUE, offset),
+                AttributeType::NormalizedUByte => gl::VertexArrayAttribFormat(vertex_array, location, components, gl::UNSIGNED_BYTE, gl::TRUE, offset),
+                AttributeType::Int => gl::VertexArrayAttribIFormat(vertex_array, location, components, gl::INT, offset),
+                AttributeType::UInt => gl::VertexArrayAttribIFormat(vertex_array, location, components, gl::UNSIGNED_INT, offset)
+            }
+            gl::VertexArrayAttribBinding(vertex_array, location, binding);
+            gl::EnableVertexArrayAttrib(vertex_array, location);
+        }
+    }
+
+    fn vertex_array_buffer(&self, vertex_array: u32, binding: u32, buffer: u32, offset: usize, stride: usize, divisor: u32) {
+        unsafe {
+            gl::VertexArrayVertexBuffer(vertex_array, binding, buffer, offset as gl::types::GLintptr, stride as gl::types::GLsizei);
+            gl::VertexArrayBindingDivisor(vertex_array, binding, divisor);
+        }
+    }
+
+    fn vertex_array_index_buffer(&self, vertex_array: u32, buffer: u32) {
+        unsafe {
+            gl::VertexArrayElementBuffer(vertex_array, buffer);
+        }
+    }
+
+    fn create_texture(&self, width: u32, height: u32, format: TextureFormat, pixels: &[u8]) -> Result<u32, GamekitError> {
+
+        if width == 0 || height == 0 {
+            return Err(GamekitError::invalid_argument(format!("invalid texture size {}x{}", width, height)));
+        }
+
+        // half float pixels are passed as raw 16 bit values
+        let (gl_internal_format, gl_format, gl_type) = match format {
+            TextureFormat::Rgb8 => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE),
+            TextureFormat::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::Rgba16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT)
+        };
+
+        // full mipmap chain down to 1x1
+        let levels = 32 - width.max(height).max(1).leading_zeros();
+
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, PIXEL_ALIGNMENT);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, PIXEL_ALIGNMENT);
+
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_S, gl::REPEAT as gl::types::GLint);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_T, gl::REPEAT as gl::types::GLint);
+            gl::TextureParameteri(id, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as gl::types::GLint);
+
+            gl::TextureStorage2D(id, levels as gl::types::GLsizei, gl_internal_format, width as gl::types::GLsizei, height as gl::types::GLsizei);
+
+            gl::TextureSubImage2D(
+                id,
+                0,
+                0,
+                0,
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
+                gl_format,
+                gl_type,
+                pixels.as_ptr() as *const c_void
+            );
+
+            let result = gl::GetError();
+            if result != gl::NO_ERROR {
+                gl::DeleteTextures(1, &id);
+                return Err(GamekitError::gl("TextureStorage2D", result));
+            }
+
+            gl::GenerateTextureMipmap(id);
+        };
+
+        return Ok(id);
+    }
+
+    fn delete_texture(&self, id: u32) {
+        if id != 0 {
+            unsafe {
+                gl::DeleteTextures(1, &id);
+            }
+        }
+    }
+
+    fn bind_texture(&self, id: u32, bind_location: u32) {
+        unsafe {
+            gl::BindTextureUnit(bind_location, id);
+        }
+    }
+
+    fn unbind_texture(&self, bind_location: u32) {
+        unsafe {
+            gl::BindTextureUnit(bind_location, 0);
+        }
+    }
+
+    fn create_render_texture(&self, width: u32, height: u32, format: TextureFormat) -> Result<u32, GamekitError> {
+
+        if width == 0 || height == 0 {
+            return Err(GamekitError::invalid_argument(format!("invalid texture size {}x{}", width, height)));
+        }
+
+        let gl_format = match format {
+            TextureFormat::Rgb8 => gl::RGB8,
+            TextureFormat::Rgba8 => gl::RGBA8,
+            TextureFormat::Rgba16F => gl::RGBA16F
+        };
+
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
+
+            // no mipmaps, the content changes every frame
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TextureParameteri(id, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, gl::LINEAR as gl::types::GLint);
+
+            gl::TextureStorage2D(id, 1, gl_format, width as gl::types::GLsizei, height as gl::types::GLsizei);
+
+            let result = gl::GetError();
+            if result != gl::NO_ERROR {
+                gl::DeleteTextures(1, &id);
+                return Err(GamekitError::gl("TextureStorage2D", result));
+            }
+        }
+
+        return Ok(id);
+    }
+
+    fn create_renderbuffer(&self, width: u32, height: u32, format: DepthStencilFormat) -> Result<u32, GamekitError> {
+
+        let gl_format = match format {
+            DepthStencilFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            DepthStencilFormat::Depth32F => gl::DEPTH_COMPONENT32F,
+            DepthStencilFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8
+        };
+
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl_format, width as gl::types::GLsizei, height as gl::types::GLsizei);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            let result = gl::GetError();
+            if result != gl::NO_ERROR {
+                gl::DeleteRenderbuffers(1, &id);
+                return Err(GamekitError::gl("RenderbufferStorage", result));
+            }
+        }
+
+        return Ok(id);
+    }
+
+    fn delete_renderbuffer(&self, id: u32) {
+        if id != 0 {
+            unsafe {
+                gl::DeleteRenderbuffers(1, &id);
+            }
+        }
+    }
+
+    fn create_framebuffer(&self, color_attachments: &[u32], depth_stencil: Option<(u32, DepthStencilFormat)>) -> Result<u32, GamekitError> {
+
+        let mut id: gl::types::GLuint = 0;
+        let draw_buffers: Vec<gl::types::GLenum> = (0..color_attachments.len() as u32).map(|index| gl::COLOR_ATTACHMENT0 + index).collect();
+
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+
+            for (index, texture) in color_attachments.iter().enumerate() {
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0 + index as u32, gl::TEXTURE_2D, *texture, 0);
+            }
+
+            gl::DrawBuffers(draw_buffers.len() as gl::types::GLsizei, draw_buffers.as_ptr());
+
+            if let Some((renderbuffer, format)) = depth_stencil {
+                let attachment = if format.has_stencil() { gl::DEPTH_STENCIL_ATTACHMENT } else { gl::DEPTH_ATTACHMENT };
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, renderbuffer);
+            }
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.current_framebuffer.get());
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                gl::DeleteFramebuffers(1, &id);
+                return Err(GamekitError::gl("CheckFramebufferStatus", status));
+            }
+        }
+
+        return Ok(id);
+    }
+
+    fn delete_framebuffer(&self, id: u32) {
+        if id != 0 {
+            if self.current_framebuffer.get() == id {
+                self.bind_framebuffer(0);
+            }
+            unsafe {
+                gl::DeleteFramebuffers(1, &id);
+            }
+        }
+    }
+
+    fn bind_framebuffer(&self, id: u32) {
+        let id = if id != 0 { id } else { self.default_framebuffer };
+        self.current_framebuffer.set(id);
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+        }
+    }
+
+    fn read_framebuffer(&self, id: u32, attachment: u32, width: u32, height: u32) -> Vec<u8> {
+
+        let id = if id != 0 { id } else { self.default_framebuffer };
+
+        let bytes_per_line = width as usize * 4;
+        let mut pixels = vec![0u8; bytes_per_line * height as usize];
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, id);
+            if id != 0 {
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + attachment);
+            }
+            gl::PixelStorei(gl::PACK_ALIGNMENT, PIXEL_ALIGNMENT);
+            gl::ReadPixels(
+                0,
+                0,
+                width as gl::types::GLsizei,
+                height as gl::types::GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut c_void
+            );
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.current_framebuffer.get());
+        }
+
+        return flip_rows(&pixels, bytes_per_line);
+    }
+
+    fn supports_spirv(&self) -> bool {
+        return self.spirv_supported;
+    }
+
+    fn create_shader(&self, shader_type: ShaderType, code: ShaderCode) -> Result<u32, GamekitError> {
+
+        let kind: gl::types::GLuint;
+
+        match shader_type {
+            ShaderType::VertexShader => { kind = gl::VERTEX_SHADER; },
+            ShaderType::TessControlShader => { kind = gl::TESS_CONTROL_SHADER; },
+            ShaderType::TessEvaluationShader => { kind = gl::TESS_EVALUATION_SHADER; },
+            ShaderType::GeometryShader => { kind = gl::GEOMETRY_SHADER; },
+            ShaderType::FragmentShader => { kind = gl::FRAGMENT_SHADER; },
+            ShaderType::ComputeShader => { kind = gl::COMPUTE_SHADER; }
+        }
+
+        let id = unsafe { gl::CreateShader(kind) };
+
+        match code {
+            ShaderCode::Binary(shader_binary, entry_point, constants) => {
+
+                let entry_point = match CString::new(entry_point) {
+                    Ok(entry_point) => entry_point,
+                    Err(_) => {
+                        unsafe { gl::DeleteShader(id); }
+                        return Err(GamekitError::invalid_argument("shader entry point contains nul bytes"));
+                    }
+                };
+
+                let constant_ids: Vec<gl::types::GLuint> = constants.iter().map(|(constant_id, _)| *constant_id).collect();
+                let constant_values: Vec<gl::types::GLuint> = constants.iter().map(|(_, value)| *value).collect();
+
+                unsafe {
+                    gl::ShaderBinary(1, &id, gl::SHADER_BINARY_FORMAT_SPIR_V, shader_binary.as_ptr() as *const c_void, shader_binary.len() as gl::types::GLsizei);
+                    gl::SpecializeShader(id, entry_point.as_ptr(), constants.len() as gl::types::GLuint, constant_ids.as_ptr(), constant_values.as_ptr());
+                }
+            },
+            ShaderCode::Source(shader_source) => {
+
+                let source = match CString::new(shader_source) {
+                    Ok(source) => source,
+                    Err(_) => {
+                        unsafe { gl::DeleteShader(id); }
+                        return Err(GamekitError::invalid_argument("shader source contains nul bytes"));
+                    }
+                };
+
+                unsafe {
+                    gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
+                    gl::CompileShader(id);
+                }
+            }
+        }
+
+        let mut success: gl::types::GLint = 0;
+        unsafe {
+            gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            let mut len: gl::types::GLint = 0;
+            unsafe {
+                gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
+            }
+
+            let error = create_whitespace_cstring_with_len(len as usize);
+
+            unsafe {
+                gl::GetShaderInfoLog(
+                    id,
+                    len,
+                    std::ptr::null_mut(),
+                    error.as_ptr() as *mut gl::types::GLchar
+                );
+
+                gl::DeleteShader(id);
+            }
+
+            return Err(GamekitError::ShaderCompile { file: String::new(), log: error.to_string_lossy().into_owned() });
+        }
+
+        return Ok(id);
+    }
+
+    fn delete_shader(&self, id: u32) {
+        if id != 0 {
+            unsafe {
+                gl::DeleteShader(id);
+            }
+        }
+    }
+
+    fn create_program(&self, shaders: &[u32]) -> Result<u32, GamekitError> {
+
+        let id = unsafe { gl::CreateProgram() };
+
+        unsafe {
+            // allows caching the linked program with glGetProgramBinary
+            gl::ProgramParameteri(id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as gl::types::GLint);
+
+            for shader in shaders {
+                gl::AttachShader(id, *shader);
+            }
+        }
+
+        unsafe {
+            gl::LinkProgram(id);
+        }
+
+        unsafe {
+            for shader in shaders {
+                gl::DetachShader(id, *shader);
+            }
+        }
+
+        let mut success: gl::types::GLint = 0;
+        unsafe {
+            gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            let log = program_info_log(id);
+            unsafe {
+                gl::DeleteProgram(id);
+            }
+
+            return Err(GamekitError::ProgramLink { log });
+        }
+
+        return Ok(id);
+    }
+
+    fn program_binary(&self, program: u32) -> Option<(u32, Vec<u8>)> {
+
+        let mut length: gl::types::GLint = 0;
+        unsafe {
+            gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+
+        // drivers without binary formats report 0
+        if length <= 0 {
+            return None;
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let mut written: gl::types::GLsizei = 0;
+        let mut format: gl::types::GLenum = 0;
+
+        unsafe {
+            gl::GetProgramBinary(program, length, &mut written, &mut format, binary.as_mut_ptr() as *mut c_void);
+        }
+
+        if written <= 0 {
+            return None;
+        }
+
+        binary.truncate(written as usize);
+
+        return Some((format, binary));
+    }
+
+    fn create_program_from_binary(&self, format: u32, binary: &[u8]) -> Result<u32, GamekitError> {
+
+        let id = unsafe { gl::CreateProgram() };
+
+        let mut success: gl::types::GLint = 0;
+
+        unsafe {
+            gl::ProgramParameteri(id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as gl::types::GLint);
+            gl::ProgramBinary(id, format, binary.as_ptr() as *const c_void, binary.len() as gl::types::GLsizei);
+            // unknown formats raise GL_INVALID_ENUM besides failing the link
+            gl::GetError();
+            gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            let log = program_info_log(id);
+            unsafe {
+                gl::DeleteProgram(id);
+            }
+
+            return Err(GamekitError::ProgramLink { log });
+        }
+
+        return Ok(id);
+    }
+
+    fn driver_id(&self) -> String {
+        return [gl::VENDOR, gl::RENDERER, gl::VERSION].iter()
+            .map(|name| {
+                let value = unsafe { gl::GetString(*name) };
+                if value.is_null() {
+                    String::new()
+                } else {
+                    unsafe { CStr::from_ptr(value as *const _) }.to_string_lossy().into_owned()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" / ");
+    }
+
+    fn delete_program(&self, id: u32) {
+        if id != 0 {
+            unsafe {
+                gl::DeleteProgram(id);
+            }
+        }
+    }
+
+    fn use_program(&self, id: u32) {
+        unsafe {
+            gl::UseProgram(id);
+        }
+    }
+
+    fn current_program(&self) -> u32 {
+        let mut program_id: gl::types::GLint = 0;
+
+        unsafe {
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program_id as *mut i32);
+        }
+
+        return program_id as u32;
+    }
+
+    fn program_interface(&self, program: u32) -> ProgramInterface {
+
+        let mut interface = ProgramInterface::default();
+
+        // uniforms in blocks are listed with their block
+        let mut uniform_block_members: Vec<Vec<BlockMember>> = vec![Vec::new(); resource_count(program, gl::UNIFORM_BLOCK)];
+
+        for index in 0..resource_count(program, gl::UNIFORM) {
+
+            let [data_type, array_size, location, block_index, offset, array_stride, matrix_stride] =
+                resource_properties(program, gl::UNIFORM, index, [gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION, gl::BLOCK_INDEX, gl::OFFSET, gl::ARRAY_STRIDE, gl::MATRIX_STRIDE]);
+
+            let name = resource_name(program, gl::UNIFORM, index);
+            let data_type = map_data_type(data_type as gl::types::GLenum);
+
+            if block_index >= 0 {
+                if let Some(members) = uniform_block_members.get_mut(block_index as usize) {
+                    members.push(BlockMember {
+                        name,
+                        data_type,
+                        array_size: array_size as u32,
+                        offset: offset as usize,
+                        array_stride: array_stride as usize,
+                        matrix_stride: matrix_stride as usize
+                    });
+                }
+                continue;
+            }
+
+            let binding = if data_type.is_opaque() && location >= 0 {
+                let mut unit: gl::types::GLint = 0;
+                unsafe { gl::GetUniformiv(program, location, &mut unit); }
+                Some(unit as u32)
+            } else {
+                None
+            };
+
+            interface.uniforms.push(UniformInfo { name, data_type, array_size: array_size as u32, location, binding });
+        }
+
+        interface.uniform_blocks = uniform_block_members.into_iter().enumerate()
+            .map(|(index, members)| block_info(program, gl::UNIFORM_BLOCK, index, members))
+            .collect();
+
+        let mut storage_block_members: Vec<Vec<BlockMember>> = vec![Vec::new(); resource_count(program, gl::SHADER_STORAGE_BLOCK)];
+
+        for index in 0..resource_count(program, gl::BUFFER_VARIABLE) {
+
+            let [data_type, array_size, block_index, offset, array_stride, matrix_stride] =
+                resource_properties(program, gl::BUFFER_VARIABLE, index, [gl::TYPE, gl::ARRAY_SIZE, gl::BLOCK_INDEX, gl::OFFSET, gl::ARRAY_STRIDE, gl::MATRIX_STRIDE]);
+
+            if let Some(members) = storage_block_members.get_mut(block_index as usize) {
+                members.push(BlockMember {
+                    name: resource_name(program, gl::BUFFER_VARIABLE, index),
+                    data_type: map_data_type(data_type as gl::types::GLenum),
+                    array_size: array_size as u32,
+                    offset: offset as usize,
+                    array_stride: array_stride as usize,
+                    matrix_stride: matrix_stride as usize
+                });
+            }
+        }
+
+        interface.storage_blocks = storage_block_members.into_iter().enumerate()
+            .map(|(index, members)| block_info(program, gl::SHADER_STORAGE_BLOCK, index, members))
+            .collect();
+
+        for index in 0..resource_count(program, gl::PROGRAM_INPUT) {
+
+            let [data_type, array_size, location] = resource_properties(program, gl::PROGRAM_INPUT, index, [gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION]);
+
+            // built-in inputs like gl_VertexID have no location
+            if location < 0 {
+                continue;
+            }
+
+            interface.attributes.push(AttributeInfo {
+                name: resource_name(program, gl::PROGRAM_INPUT, index),
+                data_type: map_data_type(data_type as gl::types::GLenum),
+                array_size: array_size as u32,
+                location
+            });
+        }
+
+        interface.uniforms.sort_by_key(|uniform| uniform.location);
+        interface.attributes.sort_by_key(|attribute| attribute.location);
+
+        return interface;
+    }
+
+    fn get_uniform_location(&self, program: u32, name: &str) -> i32 {
+
+        let cname = match CString::new(name) {
+            Ok(cname) => cname,
+            Err(_) => { return -1; }
+        };
+
+        let location: i32;
+
+        unsafe {
+            location = gl::GetUniformLocation(program as gl::types::GLuint, cname.as_bytes_with_nul().as_ptr() as *const i8) as i32;
+        }
+
+        return location;
+    }
+
+    fn set_uniform(&self, program: u32, location: i32, value: UniformValue) {
+
+        let location = location as gl::types::GLint;
+
+        unsafe {
+            match value {
+                UniformValue::Float(value) => gl::ProgramUniform1f(program, location, value),
+                UniformValue::Vec2(value) => gl::ProgramUniform2f(program, location, value.x, value.y),
+                UniformValue::Vec3(value) => gl::ProgramUniform3f(program, location, value.x, value.y, value.z),
+                UniformValue::Vec4(value) => gl::ProgramUniform4f(program, location, value.x, value.y, value.z, value.w),
+                UniformValue::Int(value) => gl::ProgramUniform1i(program, location, value),
+                UniformValue::IVec2(value) => gl::ProgramUniform2i(program, location, value.x, value.y),
+                UniformValue::UInt(value) => gl::ProgramUniform1ui(program, location, value),
+                UniformValue::Bool(value) => gl::ProgramUniform1i(program, location, value as gl::types::GLint),
+                UniformValue::Mat2(value) => gl::ProgramUniformMatrix2fv(program, location, 1, gl::FALSE, value.columns.as_ptr() as *const f32),
+                UniformValue::Mat3(value) => gl::ProgramUniformMatrix3fv(program, location, 1, gl::FALSE, value.columns.as_ptr() as *const f32),
+                UniformValue::Mat4(value) => gl::ProgramUniformMatrix4fv(program, location, 1, gl::FALSE, value.columns.as_ptr() as *const f32),
+                UniformValue::Sampler(unit) => gl::ProgramUniform1i(program, location, unit as gl::types::GLint)
+            }
+        }
+    }
+
+    fn set_uniform_array(&self, program: u32, location: i32, values: &[UniformValue]) {
+
+        if !UniformValue::is_homogeneous(values) {
+            warn!("ignoring uniform array with mixed or no element types");
+            return;
+        }
+
+        let first = values[0];
+
+        let location = location as gl::types::GLint;
+        let count = values.len() as gl::types::GLsizei;
+
+        // the components of all values in the layout of the glUniform*v call
+        let mut floats: Vec<f32> = Vec::new();
+        let mut ints: Vec<i32> = Vec::new();
+        let mut uints: Vec<u32> = Vec::new();
+
+        for value in values {
+            match *value {
+                UniformValue::Float(value) => floats.push(value),
+                UniformValue::Vec2(value) => floats.extend([value.x, value.y]),
+                UniformValue::Vec3(value) => floats.extend([value.x, value.y, value.z]),
+                UniformValue::Vec4(value) => floats.extend([value.x, value.y, value.z, value.w]),
+                UniformValue::Int(value) => ints.push(value),
+                UniformValue::IVec2(value) => ints.extend([value.x, value.y]),
+                UniformValue::UInt(value) => uints.push(value),
+                UniformValue::Bool(value) => ints.push(value as i32),
+                UniformValue::Mat2(value) => floats.extend(value.columns.iter().flatten()),
+                UniformValue::Mat3(value) => floats.extend(value.columns.iter().flatten()),
+                UniformValue::Mat4(value) => floats.extend(value.columns.iter().flatten()),
+                UniformValue::Sampler(unit) => ints.push(unit as i32)
+            }
+        }
+
+        unsafe {
+            match first {
+                UniformValue::Float(_) => gl::ProgramUniform1fv(program, location, count, floats.as_ptr()),
+                UniformValue::Vec2(_) => gl::ProgramUniform2fv(program, location, count, floats.as_ptr()),
+                UniformValue::Vec3(_) => gl::ProgramUniform3fv(program, location, count, floats.as_ptr()),
+                UniformValue::Vec4(_) => gl::ProgramUniform4fv(program, location, count, floats.as_ptr()),
+                UniformValue::Int(_) | UniformValue::Bool(_) | UniformValue::Sampler(_) => gl::ProgramUniform1iv(program, location, count, ints.as_ptr()),
+                UniformValue::IVec2(_) => gl::ProgramUniform2iv(program, location, count, ints.as_ptr()),
+                UniformValue::UInt(_) => gl::ProgramUniform1uiv(program, location, count, uints.as_ptr()),
+                UniformValue::Mat2(_) => gl::ProgramUniformMatrix2fv(program, location, count, gl::FALSE, floats.as_ptr()),
+                UniformValue::Mat3(_) => gl::ProgramUniformMatrix3fv(program, location, count, gl::FALSE, floats.as_ptr()),
+                UniformValue::Mat4(_) => gl::ProgramUniformMatrix4fv(program, location, count, gl::FALSE, floats.as_ptr())
+            }
+        }
+    }
+
+}
+
+// program interface queries (GL 4.3)
+
+fn resource_count(program: u32, interface: gl::types::GLenum) -> usize {
+    let mut count: gl::types::GLint = 0;
+    unsafe { gl::GetProgramInterfaceiv(program, interface, gl::ACTIVE_RESOURCES, &mut count); }
+    return count.max(0) as usize;
+}
+
+fn resource_properties<const N: usize>(program: u32, interface: gl::types::GLenum, index: usize, properties: [gl::types::GLenum; N]) -> [gl::types::GLint; N] {
+    let mut values = [0 as gl::types::GLint; N];
+    unsafe {
+        gl::GetProgramResourceiv(program, interface, index as gl::types::GLuint, N as gl::types::GLsizei, properties.as_ptr(),
+            N as gl::types::GLsizei, std::ptr::null_mut(), values.as_mut_ptr());
+    }
+    return values;
+}
+
+fn resource_name(program: u32, interface: gl::types::GLenum, index: usize) -> String {
+
+    let [length] = resource_properties(program, interface, index, [gl::NAME_LENGTH]);
+    if length <= 0 {
+        return String::new();
+    }
+
+    let mut name = vec![0u8; length as usize];
+    let mut written: gl::types::GLsizei = 0;
+    unsafe {
+        gl::GetProgramResourceName(program, interface, index as gl::types::GLuint, length, &mut written, name.as_mut_ptr() as *mut gl::types::GLchar);
+    }
+    name.truncate(written.max(0) as usize);
+
+    return String::from_utf8_lossy(&name).into_owned();
+}
+
+fn block_info(program: u32, interface: gl::types::GLenum, index: usize, mut members: Vec<BlockMember>) -> BlockInfo {
+
+    let [binding, data_size] = resource_properties(program, interface, index, [gl::BUFFER_BINDING, gl::BUFFER_DATA_SIZE]);
+
+    members.sort_by_key(|member| member.offset);
+
+    return BlockInfo {
+        name: resource_name(program, interface, index),
+        binding: binding as u32,
+        data_size: data_size as usize,
+        members
+    };
+}
+
+fn map_data_type(data_type: gl::types::GLenum) -> DataType {
+    match data_type {
+        gl::FLOAT => DataType::Float,
+        gl::FLOAT_VEC2 => DataType::Vec2,
+        gl::FLOAT_VEC3 => DataType::Vec3,
+        gl::FLOAT_VEC4 => DataType::Vec4,
+        gl::INT => DataType::Int,
+        gl::INT_VEC2 => DataType::IVec2,
+        gl::INT_VEC3 => DataType::IVec3,
+        gl::INT_VEC4 => DataType::IVec4,
+        gl::UNSIGNED_INT => DataType::UInt,
+        gl::UNSIGNED_INT_VEC2 => DataType::UVec2,
+        gl::UNSIGNED_INT_VEC3 => DataType::UVec3,
+        gl::UNSIGNED_INT_VEC4 => DataType::UVec4,
+        gl::BOOL => DataType::Bool,
+        gl::BOOL_VEC2 => DataType::BVec2,
+        gl::BOOL_VEC3 => DataType::BVec3,
+        gl::BOOL_VEC4 => DataType::BVec4,
+        gl::FLOAT_MAT2 => DataType::Mat2,
+        gl::FLOAT_MAT3 => DataType::Mat3,
+        gl::FLOAT_MAT4 => DataType::Mat4,
+        gl::FLOAT_MAT2x3 => DataType::Mat2x3,
+        gl::FLOAT_MAT2x4 => DataType::Mat2x4,
+        gl::FLOAT_MAT3x2 => DataType::Mat3x2,
+        gl::FLOAT_MAT3x4 => DataType::Mat3x4,
+        gl::FLOAT_MAT4x2 => DataType::Mat4x2,
+        gl::FLOAT_MAT4x3 => DataType::Mat4x3,
+        gl::SAMPLER_2D => DataType::Sampler2D,
+        gl::SAMPLER_3D => DataType::Sampler3D,
+        gl::SAMPLER_CUBE => DataType::SamplerCube,
+        gl::SAMPLER_2D_ARRAY => DataType::Sampler2DArray,
+        gl::SAMPLER_2D_SHADOW => DataType::Sampler2DShadow,
+        gl::IMAGE_2D => DataType::Image2D,
+        other => DataType::Other(other)
+    }
+}
+
+fn map_draw_mode(mode: &DrawMode) -> gl::types::GLenum {
+    match mode {
+        DrawMode::Triangles => gl::TRIANGLES,
+        DrawMode::TriangleStrip => gl::TRIANGLE_STRIP
+    }
+}
+
+fn map_usage(usage: &BufferUsage) -> gl::types::GLenum {
+    match usage {
+        BufferUsage::StreamDraw => gl::STREAM_DRAW,
+        BufferUsage::StreamRead => gl::STREAM_READ,
+        BufferUsage::StreamCopy => gl::STREAM_COPY,
+        BufferUsage::StaticDraw => gl::STATIC_DRAW,
+        BufferUsage::StaticRead => gl::STATIC_READ,
+        BufferUsage::StaticCopy => gl::STATIC_COPY,
+        BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
+        BufferUsage::DynamicRead => gl::DYNAMIC_READ,
+        BufferUsage::DynamicCopy => gl::DYNAMIC_COPY,
+    }
+}
+
+fn map_type(buffer_type: &BufferType) -> gl::types::GLenum  {
+    match buffer_type {
+        BufferType::ArrayBuffer => gl::ARRAY_BUFFER,
+        BufferType::IndexBuffer => gl::ELEMENT_ARRAY_BUFFER,
+        BufferType::UniformBuffer => gl::UNIFORM_BUFFER,
+        BufferType::ShaderStorageBuffer => gl::SHADER_STORAGE_BUFFER,
+        BufferType::DispatchIndirectBuffer => gl::DISPATCH_INDIRECT_BUFFER
+    }
+}
+
+fn map_barrier(barrier: &MemoryBarrier) -> gl::types::GLbitfield {
+    match barrier {
+        MemoryBarrier::VertexAttribArray => gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT,
+        MemoryBarrier::ElementArray => gl::ELEMENT_ARRAY_BARRIER_BIT,
+        MemoryBarrier::Uniform => gl::UNIFORM_BARRIER_BIT,
+        MemoryBarrier::TextureFetch => gl::TEXTURE_FETCH_BARRIER_BIT,
+        MemoryBarrier::ShaderImageAccess => gl::SHADER_IMAGE_ACCESS_BARRIER_BIT,
+        MemoryBarrier::Command => gl::COMMAND_BARRIER_BIT,
+        MemoryBarrier::BufferUpdate => gl::BUFFER_UPDATE_BARRIER_BIT,
+        MemoryBarrier::Framebuffer => gl::FRAMEBUFFER_BARRIER_BIT,
+        MemoryBarrier::ShaderStorage => gl::SHADER_STORAGE_BARRIER_BIT,
+        MemoryBarrier::All => gl::ALL_BARRIER_BITS
+    }
+}
+
+fn create_whitespace_cstring_with_len(len: usize) -> CString {
+    // allocate buffer of correct size
+    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
+    // fill it with len spaces
+    buffer.extend([b' '].iter().cycle().take(len));
+    // convert buffer to CString
+    unsafe { CString::from_vec_unchecked(buffer) }
+}
+
+// GL rows start at the bottom, images at the top
+fn flip_rows(pixels: &[u8], bytes_per_line: usize) -> Vec<u8> {
+    let mut flipped = vec![0u8; pixels.len()];
+    for (row, line) in pixels.chunks_exact(bytes_per_line).rev().enumerate() {
+        flipped[row * bytes_per_line..(row + 1) * bytes_per_line].copy_from_slice(line);
+    }
+    return flipped;
+}
+
+fn program_info_log(id: u32) -> String {
+
+    let mut len: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
+    }
+
+    let error = create_whitespace_cstring_with_len(len as usize);
+
+    unsafe {
+        gl::GetProgramInfoLog(
+            id,
+            len,
+            std::ptr::null_mut(),
+            error.as_ptr() as *mut gl::types::GLchar
+        );
+    }
+
+    return error.to_string_lossy().into_owned();
+}
+
+fn gl_version() -> (i32, i32) {
+    let mut major: gl::types::GLint = 0;
+    let mut minor: gl::types::GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    return (major, minor);
+}
+
+fn has_extension(name: &str) -> bool {
+    let mut count: gl::types::GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    }
+
+    return (0..count as u32).any(|index| {
+        let extension = unsafe { gl::GetStringi(gl::EXTENSIONS, index) };
+        !extension.is_null() && unsafe { CStr::from_ptr(extension as *const _) }.to_bytes() == name.as_bytes()
+    });
+}
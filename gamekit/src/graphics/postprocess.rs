@@ -0,0 +1,311 @@
+//
+// Post Processing
+//
+
+use super::{
+    api::{Api, BlendMode},
+    buffers::{BufferUsage, UniformBufferObject},
+    error::GamekitError,
+    primitives::{FullscreenTriangle, Primitives},
+    program::Program,
+    render_target::RenderTarget,
+    shader::{Shader, ShaderType},
+    texture::Texture,
+    types::{Vector2, Vector4},
+    layout::BlockLayout
+};
+
+defaults!();
+
+const SHADER_DIRECTORY: &str = "shaders";
+const FULLSCREEN_VERTEX_SHADER: &str = "fullscreen.vert";
+
+// binding points shared by all pass shaders
+const PASS_UNIFORM_BINDING: u32 = 3;
+const SOURCE_TEXTURE_UNIT: u32 = 0;
+const SCENE_TEXTURE_UNIT: u32 = 1;
+const LOOKUP_TEXTURE_UNIT: u32 = 2;
+
+// the pass_uniforms block
+#[derive(Clone, Copy, BlockLayout)]
+struct PassUniforms {
+    resolution: Vector2,
+    texel_size: Vector2,
+    time: f32,
+    frame: i32,
+    params: [Vector4; 4]
+}
+
+impl Default for PassUniforms {
+    fn default() -> PassUniforms {
+        return PassUniforms {
+            resolution: Vector2::zero(),
+            texel_size: Vector2::zero(),
+            time: 0.0,
+            frame: 0,
+            params: [Vector4::zero(); 4]
+        };
+    }
+}
+
+// Fullscreen fragment shader pass. Pass shaders sample the previous pass
+// output (iSource, unit 0), the untouched scene (iScene, unit 1) and an
+// optional lookup texture (iLookup, unit 2), and read their parameters from
+// the pass_uniforms block at binding 3, see shaders/post_*.frag.
+pub struct PostProcessPass {
+    pub name: String,
+    pub enabled: bool,
+    program: Program,
+    uniforms: PassUniforms,
+    uniform_buffer: UniformBufferObject<PassUniforms>,
+    lookup_texture: Option<Texture>
+}
+
+impl PostProcessPass {
+
+    // loads shaders/<fragment_shader>.spv together with the fullscreen vertex shader
+    pub fn new(name: &str, fragment_shader: &str) -> Result<PostProcessPass, GamekitError> {
+
+        let vertex_shader = Shader::new(ShaderType::VertexShader, &shader_path(FULLSCREEN_VERTEX_SHADER))?;
+        let fragment_shader = Shader::new(ShaderType::FragmentShader, &shader_path(fragment_shader))?;
+        let program = Program::new(&vec!(vertex_shader, fragment_shader))?;
+
+        let pass = PostProcessPass {
+            name: name.to_string(),
+            enabled: true,
+            program,
+            uniforms: PassUniforms::default(),
+            uniform_buffer: UniformBufferObject::new(PASS_UNIFORM_BINDING, BufferUsage::DynamicDraw)?,
+            lookup_texture: None
+        };
+
+        return Ok(pass);
+    }
+
+    // sets params[index] of the pass uniform block, index 0 to 3
+    pub fn set_parameter(&mut self, index: usize, x: f32, y: f32, z: f32, w: f32) -> Result<(), GamekitError> {
+        let num_params = self.uniforms.params.len();
+        let param = match self.uniforms.params.get_mut(index) {
+            Some(param) => param,
+            None => { return Err(GamekitError::invalid_argument(format!("pass parameter index {} out of range 0..{}", index, num_params))); }
+        };
+        *param = Vector4::new(x, y, z, w);
+        return Ok(());
+    }
+
+    pub fn parameter(&self, index: usize) -> Option<[f32; 4]> {
+        let param = self.uniforms.params.get(index)?;
+        return Some([param.x, param.y, param.z, param.w]);
+    }
+
+    pub fn set_lookup_texture(&mut self, texture: Texture) {
+        self.lookup_texture = Some(texture);
+    }
+
+    fn apply(&mut self, api: &dyn Api, triangle: &FullscreenTriangle, source: &Texture, scene: &Texture, width: u32, height: u32) {
+
+        let metrics = api.get_metrics();
+
+        self.uniforms.resolution = Vector2::new(width as f32, height as f32);
+        self.uniforms.texel_size = Vector2::new(1.0 / source.width().max(1) as f32, 1.0 / source.height().max(1) as f32);
+        self.uniforms.time = metrics.time_seconds;
+        self.uniforms.frame = metrics.frame_counter as i32;
+
+        self.uniform_buffer.copy_to_buffer(&self.uniforms);
+        self.uniform_buffer.bind_base();
+
+        self.program.use_program();
+
+        source.bind(SOURCE_TEXTURE_UNIT);
+        scene.bind(SCENE_TEXTURE_UNIT);
+        if let Some(lookup_texture) = &self.lookup_texture {
+            lookup_texture.bind(LOOKUP_TEXTURE_UNIT);
+        }
+
+        triangle.draw(api);
+    }
+
+}
+
+// Renders the scene into an offscreen target and runs the enabled passes,
+// ping-ponging between two render targets. The last pass writes to the
+// default framebuffer:
+//
+//     chain.begin(api);
+//     ... draw the scene ...
+//     chain.end(api);
+pub struct PostProcessChain {
+    passes: Vec<PostProcessPass>,
+    copy_pass: PostProcessPass,
+    scene: RenderTarget,
+    targets: [RenderTarget; 2],
+    triangle: FullscreenTriangle
+}
+
+impl PostProcessChain {
+
+    pub fn new(width: u32, height: u32) -> Result<PostProcessChain, GamekitError> {
+
+        let chain = PostProcessChain {
+            passes: Vec::new(),
+            copy_pass: PostProcessPass::new("copy", "post_copy.frag")?,
+            scene: RenderTarget::with_size(width, height)?,
+            targets: [RenderTarget::with_size(width, height)?, RenderTarget::with_size(width, height)?],
+            triangle: FullscreenTriangle::new()
+        };
+
+        return Ok(chain);
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), GamekitError> {
+
+        if width == self.scene.width() && height == self.scene.height() {
+            return Ok(());
+        }
+
+        self.scene = RenderTarget::with_size(width, height)?;
+        self.targets = [RenderTarget::with_size(width, height)?, RenderTarget::with_size(width, height)?];
+
+        return Ok(());
+    }
+
+    pub fn push(&mut self, pass: PostProcessPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn passes(&self) -> &[PostProcessPass] {
+        return &self.passes;
+    }
+
+    pub fn passes_mut(&mut self) -> &mut [PostProcessPass] {
+        return &mut self.passes;
+    }
+
+    pub fn find_pass_mut(&mut self, name: &str) -> Option<&mut PostProcessPass> {
+        return self.passes.iter_mut().find(|pass| pass.name == name);
+    }
+
+    // horizontal and vertical pass ("blur_x", "blur_y")
+    pub fn add_gaussian_blur(&mut self, radius: f32) -> Result<(), GamekitError> {
+        self.push(blur_pass("blur_x", 1.0, 0.0, radius)?);
+        self.push(blur_pass("blur_y", 0.0, 1.0, radius)?);
+        return Ok(());
+    }
+
+    // bright pass, blur and additive combine with the scene ("bloom_extract", "bloom_blur_x", "bloom_blur_y", "bloom_combine")
+    pub fn add_bloom(&mut self, threshold: f32, intensity: f32, radius: f32) -> Result<(), GamekitError> {
+
+        let mut extract = PostProcessPass::new("bloom_extract", "post_bloom_extract.frag")?;
+        extract.set_parameter(0, threshold, 0.1, 0.0, 0.0)?;
+        self.push(extract);
+
+        self.push(blur_pass("bloom_blur_x", 1.0, 0.0, radius)?);
+        self.push(blur_pass("bloom_blur_y", 0.0, 1.0, radius)?);
+
+        let mut combine = PostProcessPass::new("bloom_combine", "post_bloom_combine.frag")?;
+        combine.set_parameter(0, intensity, 0.0, 0.0, 0.0)?;
+        self.push(combine);
+
+        return Ok(());
+    }
+
+    // radius and softness relative to the screen height
+    pub fn add_vignette(&mut self, strength: f32, radius: f32, softness: f32) -> Result<(), GamekitError> {
+        let mut pass = PostProcessPass::new("vignette", "post_vignette.frag")?;
+        pass.set_parameter(0, strength, radius, softness, 0.0)?;
+        self.push(pass);
+        return Ok(());
+    }
+
+    // scanline_count 0 puts a scanline on every other pixel row
+    pub fn add_crt(&mut self, curvature: f32, scanline_intensity: f32, scanline_count: f32, mask_intensity: f32) -> Result<(), GamekitError> {
+        let mut pass = PostProcessPass::new("crt", "post_crt.frag")?;
+        pass.set_parameter(0, curvature, scanline_intensity, scanline_count, mask_intensity)?;
+        self.push(pass);
+        return Ok(());
+    }
+
+    // lookup table as a size^2 x size strip, e.g. 256x16 for size 16
+    pub fn add_color_grading(&mut self, lookup_texture: Texture, intensity: f32) -> Result<(), GamekitError> {
+
+        let size = lookup_texture.height() as f32;
+        if lookup_texture.width() != lookup_texture.height() * lookup_texture.height() {
+            return Err(GamekitError::invalid_argument(format!("invalid color lookup table size {}x{}", lookup_texture.width(), lookup_texture.height())));
+        }
+
+        let mut pass = PostProcessPass::new("color_grading", "post_color_grading.frag")?;
+        pass.set_parameter(0, intensity, size, 0.0, 0.0)?;
+        pass.set_lookup_texture(lookup_texture);
+        self.push(pass);
+
+        return Ok(());
+    }
+
+    pub fn scene(&self) -> &RenderTarget {
+        return &self.scene;
+    }
+
+    // redirects drawing into the scene target
+    pub fn begin(&self, api: &dyn Api) {
+        api.set_render_target(Some(&self.scene));
+        api.clear();
+    }
+
+    pub fn end(&mut self, api: &dyn Api) {
+
+        api.set_blend_mode(BlendMode::Opaque);
+
+        let scene = self.scene.texture();
+
+        let mut passes: Vec<&mut PostProcessPass> = self.passes.iter_mut().filter(|pass| pass.enabled).collect();
+        if passes.is_empty() {
+            passes.push(&mut self.copy_pass);
+        }
+
+        let count = passes.len();
+        let mut source = scene;
+
+        for (index, pass) in passes.into_iter().enumerate() {
+
+            if index + 1 < count {
+                let target = &self.targets[index % 2];
+                api.set_render_target(Some(target));
+                pass.apply(api, &self.triangle, source, scene, target.width(), target.height());
+                source = target.texture();
+            } else {
+                api.set_render_target(None);
+                let metrics = api.get_metrics();
+                pass.apply(api, &self.triangle, source, scene, metrics.width, metrics.height);
+            }
+        }
+
+        for unit in [SOURCE_TEXTURE_UNIT, SCENE_TEXTURE_UNIT, LOOKUP_TEXTURE_UNIT] {
+            Primitives::unbind_texture(unit);
+        }
+
+        api.set_blend_mode(BlendMode::Normal);
+    }
+
+    pub fn free(&mut self) {
+        info!("PostProcessChain free");
+        self.triangle.free();
+    }
+
+}
+
+impl Drop for PostProcessChain {
+    fn drop(&mut self) {
+        debug!("drop post process chain");
+        self.free();
+    }
+}
+
+fn blur_pass(name: &str, x: f32, y: f32, radius: f32) -> Result<PostProcessPass, GamekitError> {
+    let mut pass = PostProcessPass::new(name, "post_blur.frag")?;
+    pass.set_parameter(0, x, y, radius, 0.0)?;
+    return Ok(pass);
+}
+
+fn shader_path(name: &str) -> String {
+    return format!("{}/{}.spv", SHADER_DIRECTORY, name);
+}
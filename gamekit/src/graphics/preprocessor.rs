@@ -0,0 +1,380 @@
+//
+// Shader Preprocessor
+//
+
+// Resolves #include "file" relative to the including file (every file is
+// included once) and injects #define lines after #version. Compiler messages
+// refer to lines of the combined source, PreprocessedSource::map_log maps them
+// back to the original files. specialize() applies SPIR-V specialization
+// constants to sources the driver compiles.
+//
+// Self-contained, build.rs compiles it as well.
+
+use std::{fs, io, path::Path};
+
+pub struct IncludeError {
+    pub path: String,
+    pub error: io::Error
+}
+
+pub struct PreprocessedSource {
+    pub source: String,
+    // the main file and its includes
+    pub files: Vec<String>,
+    // file index and line number for each line of the source
+    line_map: Vec<(usize, usize)>
+}
+
+impl PreprocessedSource {
+
+    // original file and line for a line number of the combined source
+    pub fn location(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, line) = *self.line_map.get(line.checked_sub(1)?)?;
+        return Some((&self.files[file], line));
+    }
+
+    // rewrites locations in messages like "0:12(3): error" (Mesa), "ERROR: 0:12:" (glslang),
+    // "0(12) : error" (NVIDIA) or "shaders/shader.vert:12:" (glslc) to "<file>:<line>"
+    pub fn map_log(&self, log: &str) -> String {
+
+        let main_file = self.files.first().map(|file| file.as_str()).unwrap_or_default();
+
+        let mut output = String::with_capacity(log.len());
+        let mut rest = log;
+        let mut at_token_start = true;
+
+        while let Some(c) = rest.chars().next() {
+
+            if at_token_start {
+
+                let prefix_length = if !main_file.is_empty() && rest.starts_with(main_file) {
+                    main_file.len()
+                } else if rest.starts_with('0') && !rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+                    // source string 0, the only one
+                    1
+                } else {
+                    0
+                };
+
+                if prefix_length > 0 {
+
+                    let (prefix, after) = rest.split_at(prefix_length);
+
+                    if let Some((location, remainder)) = self.map_location(after) {
+                        output.push_str(&location);
+                        rest = remainder;
+                    } else {
+                        output.push_str(prefix);
+                        rest = after;
+                    }
+
+                    at_token_start = false;
+                    continue;
+                }
+            }
+
+            output.push(c);
+            rest = &rest[c.len_utf8()..];
+            at_token_start = !(c.is_alphanumeric() || c == '_' || c == '.' || c == '/' || c == '\\');
+        }
+
+        return output;
+    }
+
+    // ":<line>" or "(<line>" following a source reference
+    fn map_location<'a>(&self, text: &'a str) -> Option<(String, &'a str)> {
+
+        if !text.starts_with(':') && !text.starts_with('(') {
+            return None;
+        }
+
+        let digits = &text[1..];
+        let line_length = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        let line = digits[..line_length].parse::<usize>().ok()?;
+
+        let (file, line) = self.location(line)?;
+
+        return Some((format!("{}{}{}", file, &text[..1], line), &digits[line_length..]));
+    }
+
+}
+
+pub fn preprocess(path: &str, defines: &[(&str, &str)]) -> Result<PreprocessedSource, IncludeError> {
+
+    let mut preprocessor = Preprocessor {
+        output: String::new(),
+        files: Vec::new(),
+        line_map: Vec::new(),
+        stack: Vec::new()
+    };
+
+    preprocessor.process_file(Path::new(path), Some(defines))?;
+
+    let source = PreprocessedSource {
+        source: preprocessor.output,
+        files: preprocessor.files,
+        line_map: preprocessor.line_map
+    };
+
+    return Ok(source);
+}
+
+struct Preprocessor {
+    output: String,
+    files: Vec<String>,
+    line_map: Vec<(usize, usize)>,
+    // includes being processed, for detecting cycles
+    stack: Vec<String>
+}
+
+impl Preprocessor {
+
+    // defines are given for the main file only
+    fn process_file(&mut self, path: &Path, defines: Option<&[(&str, &str)]>) -> Result<(), IncludeError> {
+
+        let name = path.to_string_lossy().to_string();
+
+        if self.stack.contains(&name) {
+            return Err(IncludeError { path: name, error: io::Error::new(io::ErrorKind::InvalidData, "recursive include") });
+        }
+
+        if defines.is_none() && self.files.contains(&name) {
+            return Ok(());
+        }
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) => { return Err(IncludeError { path: name, error }); }
+        };
+
+        let index = self.files.len();
+        self.files.push(name.clone());
+        self.stack.push(name);
+
+        let has_version = text.lines().any(|line| line.trim_start().starts_with("#version"));
+
+        if let Some(defines) = defines {
+            if !has_version {
+                self.write_defines(defines, index, 1);
+            }
+        }
+
+        for (number, line) in text.lines().enumerate() {
+
+            let trimmed = line.trim_start();
+
+            if let Some(include) = parse_include(trimmed) {
+                let include_path = path.parent().unwrap_or(Path::new("")).join(include);
+                self.process_file(&include_path, None)?;
+                continue;
+            }
+
+            self.write_line(line, index, number + 1);
+
+            if let Some(defines) = defines {
+                if trimmed.starts_with("#version") {
+                    self.write_defines(defines, index, number + 1);
+                }
+            }
+        }
+
+        self.stack.pop();
+
+        return Ok(());
+    }
+
+    fn write_line(&mut self, line: &str, file: usize, number: usize) {
+        self.output.push_str(line);
+        self.output.push('\n');
+        self.line_map.push((file, number));
+    }
+
+    // define lines are reported as the line they were injected at
+    fn write_defines(&mut self, defines: &[(&str, &str)], file: usize, number: usize) {
+        for (name, value) in defines {
+            self.write_line(&format!("#define {} {}", name, value), file, number);
+        }
+    }
+
+}
+
+// Replaces the values of specialization constants for drivers compiling GLSL,
+// "layout(constant_id = 3) const int COUNT = 4;" becomes "const int COUNT = 8;"
+// for (3, 8). Constants are (constant id, value) with floats as their bits, the
+// layout qualifier is removed from all of them. Line numbers do not change.
+pub fn specialize(source: &str, constants: &[(u32, u32)]) -> String {
+
+    let mut output = String::with_capacity(source.len());
+
+    for line in source.lines() {
+        match parse_constant(line) {
+            Some((indent, id, type_name, name, default_value)) => {
+                let value = constants.iter()
+                    .find(|(constant_id, _)| *constant_id == id)
+                    .and_then(|(_, value)| format_constant(type_name, *value))
+                    .unwrap_or_else(|| default_value.to_string());
+                output.push_str(&format!("{}const {} {} = {};", indent, type_name, name, value));
+            },
+            None => { output.push_str(line); }
+        }
+        output.push('\n');
+    }
+
+    return output;
+}
+
+// indent, constant id, type, name and value of "layout(constant_id = N) const T NAME = VALUE;"
+fn parse_constant(line: &str) -> Option<(&str, u32, &str, &str, &str)> {
+
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    let qualifiers = trimmed.strip_prefix("layout")?.trim_start().strip_prefix('(')?;
+    let (qualifiers, declaration) = qualifiers.split_at(qualifiers.find(')')?);
+
+    let (key, id) = qualifiers.split_once('=')?;
+    if key.trim() != "constant_id" {
+        return None;
+    }
+    let id = id.trim().parse::<u32>().ok()?;
+
+    let declaration = declaration[1..].trim_start().strip_prefix("const")?;
+    let (declaration, value) = declaration.split_once('=')?;
+
+    let mut words = declaration.split_whitespace();
+    let type_name = words.next()?;
+    let name = words.next()?;
+
+    let value = value.trim();
+    let value = value[..value.find(';')?].trim();
+
+    return Some((indent, id, type_name, name, value));
+}
+
+fn format_constant(type_name: &str, value: u32) -> Option<String> {
+    return match type_name {
+        "bool" => Some((value != 0).to_string()),
+        "int" => Some((value as i32).to_string()),
+        "uint" => Some(format!("{}u", value)),
+        "float" => Some(format!("{:?}", f32::from_bits(value))),
+        _ => None
+    };
+}
+
+// #include "file" or #include <file>
+fn parse_include(line: &str) -> Option<&str> {
+
+    let rest = line.strip_prefix("#include")?.trim_start();
+
+    let close = match rest.chars().next()? {
+        '"' => '"',
+        '<' => '>',
+        _ => { return None; }
+    };
+
+    let rest = &rest[1..];
+
+    return Some(&rest[..rest.find(close)?]);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+    use super::{preprocess, PreprocessedSource};
+
+    // writes the files to a fresh directory, returns the path of the first one
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = env::temp_dir().join(format!("gamekit_preprocessor_{}_{}", test, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for (name, text) in files {
+            fs::write(directory.join(name), text).unwrap();
+        }
+        return directory.join(files[0].0);
+    }
+
+    fn preprocess_files(test: &str, files: &[(&str, &str)], defines: &[(&str, &str)]) -> PreprocessedSource {
+        let path = write_files(test, files);
+        return match preprocess(path.to_str().unwrap(), defines) {
+            Ok(source) => source,
+            Err(err) => panic!("{}: {}", err.path, err.error)
+        };
+    }
+
+    fn nested_source() -> PreprocessedSource {
+        return preprocess_files("nested", &[
+            ("main.vert", "#version 450\n#include \"common.glsl\"\nvoid main() {\n    x = 1;\n}\n"),
+            ("common.glsl", "#include \"inner.glsl\"\nfloat common_value;\n"),
+            ("inner.glsl", "float inner_value;\n")
+        ], &[("COUNT", "4")]);
+    }
+
+    #[test]
+    fn nested_includes_are_inlined_after_defines() {
+        let source = nested_source();
+        assert_eq!(source.source, "#version 450\n#define COUNT 4\nfloat inner_value;\nfloat common_value;\nvoid main() {\n    x = 1;\n}\n");
+        assert_eq!(source.files.len(), 3);
+        assert_eq!(source.location(2), Some((source.files[0].as_str(), 1)));
+        assert_eq!(source.location(3), Some((source.files[2].as_str(), 1)));
+        assert_eq!(source.location(4), Some((source.files[1].as_str(), 2)));
+        assert_eq!(source.location(6), Some((source.files[0].as_str(), 4)));
+        assert_eq!(source.location(0), None);
+        assert_eq!(source.location(8), None);
+    }
+
+    #[test]
+    fn compiler_logs_refer_to_original_files() {
+        let source = nested_source();
+        let main = &source.files[0];
+        let common = &source.files[1];
+        let inner = &source.files[2];
+
+        // Mesa
+        assert_eq!(source.map_log("0:6(5): error: `x' undeclared"), format!("{}:4(5): error: `x' undeclared", main));
+        // glslang
+        assert_eq!(source.map_log("ERROR: 0:4: 'common_value' : redefinition"), format!("ERROR: {}:2: 'common_value' : redefinition", common));
+        // NVIDIA
+        assert_eq!(source.map_log("0(3) : error C0000: syntax error"), format!("{}(1) : error C0000: syntax error", inner));
+        // glslc
+        assert_eq!(source.map_log(&format!("{}:7: error: '}}' : unexpected", main)), format!("{}:5: error: '}}' : unexpected", main));
+        // numbers that are no locations are kept
+        assert_eq!(source.map_log("error 0x10 in 10:2"), "error 0x10 in 10:2");
+        assert_eq!(source.map_log("0:99: error"), "0:99: error");
+    }
+
+    #[test]
+    fn files_are_included_once() {
+        let source = preprocess_files("once", &[
+            ("main.frag", "#include \"a.glsl\"\n#include \"common.glsl\"\nvoid main() {}\n"),
+            ("a.glsl", "#include \"common.glsl\"\nfloat a;\n"),
+            ("common.glsl", "float common_value;\n")
+        ], &[]);
+        assert_eq!(source.source, "float common_value;\nfloat a;\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn recursive_includes_are_rejected() {
+        let path = write_files("cycle", &[
+            ("main.frag", "#include \"a.glsl\"\n"),
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "#include \"a.glsl\"\n")
+        ]);
+        match preprocess(path.to_str().unwrap(), &[]) {
+            Ok(_) => panic!("recursive include was accepted"),
+            Err(err) => {
+                assert!(err.path.ends_with("a.glsl"));
+                assert_eq!(err.error.to_string(), "recursive include");
+            }
+        }
+    }
+
+    #[test]
+    fn defines_without_version_start_the_source() {
+        let source = preprocess_files("defines", &[
+            ("main.frag", "float a;\n")
+        ], &[("A", "1"), ("B", "2")]);
+        assert_eq!(source.source, "#define A 1\n#define B 2\nfloat a;\n");
+        assert_eq!(source.location(2), Some((source.files[0].as_str(), 1)));
+        assert_eq!(source.location(3), Some((source.files[0].as_str(), 1)));
+    }
+}
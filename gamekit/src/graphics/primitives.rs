@@ -1,10 +1,9 @@
-use super::{gl};
-
-extern crate sdl2;
+use super::{backend, api::Api};
 
 defaults!();
 
 #[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DrawMode {
     Triangles,
     TriangleStrip
@@ -16,95 +15,65 @@ pub struct Primitives {}
 impl Primitives {
 
     pub fn viewport(x: u32, y: u32, width: u32, height: u32) {
-
-        unsafe {
-            gl::Viewport(x as gl::types::GLsizei,
-                         y as gl::types::GLsizei,
-                         width as gl::types::GLsizei,
-                         height as gl::types::GLsizei);
-        }
+        backend::current().viewport(x, y, width, height);
     }
 
     pub fn clear_color(red: f32, green: f32, blue: f32, alpha: f32) {
-        unsafe {
-            gl::ClearColor(red, green, blue, alpha);
-        }
+        backend::current().clear_color(red, green, blue, alpha);
     }
 
     pub fn clear() {
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-        }
+        backend::current().clear();
     }
 
     pub fn draw_arrays(mode: DrawMode, ofs: usize, count: usize) {
-
-        let draw_mode:  gl::types::GLenum;
-
-        match mode {
-            DrawMode::Triangles => { draw_mode = gl::TRIANGLES; },
-            DrawMode::TriangleStrip => { draw_mode = gl::TRIANGLE_STRIP; },
-        }
-
-        unsafe {
-            gl::DrawArrays(
-                draw_mode,
-                ofs as gl::types::GLint,     // starting index in the enabled arrays
-                count as gl::types::GLsizei         // number of indices to be rendered
-            );
-        }
+        backend::current().draw_arrays(mode, ofs, count);
     }
 
     pub fn draw_elements(mode: DrawMode, count: usize) {
+        backend::current().draw_elements(mode, count);
+    }
 
-        let draw_mode:  gl::types::GLenum;
+    pub fn draw_elements_instanced(mode: DrawMode, count: usize, num_instances: usize) {
+        backend::current().draw_elements_instanced(mode, count, num_instances);
+    }
 
-        match mode {
-            DrawMode::Triangles => { draw_mode = gl::TRIANGLES; },
-            DrawMode::TriangleStrip => { draw_mode = gl::TRIANGLE_STRIP; },
-        }
+    pub fn bind_texture(id: u32, bind_location: u32) {
+        backend::current().bind_texture(id, bind_location);
+    }
 
-        unsafe {
-            gl::DrawElements(
-                draw_mode,
-                count as gl::types::GLsizei,
-                gl::UNSIGNED_INT,
-                0 as *const _
-            );
-        }
+    pub fn unbind_texture(bind_location: u32) {
+        backend::current().unbind_texture(bind_location);
     }
 
-    pub fn draw_elements_instanced(mode: DrawMode, count: usize, num_instances: usize) {
+}
 
-        let draw_mode:  gl::types::GLenum;
 
-        match mode {
-            DrawMode::Triangles => { draw_mode = gl::TRIANGLES; },
-            DrawMode::TriangleStrip => { draw_mode = gl::TRIANGLE_STRIP; },
-        }
+// Fullscreen passes draw a single triangle covering the viewport, the vertex
+// shader (fullscreen.vert) derives the positions from gl_VertexID. There are no
+// attributes, but core profiles still need a bound vertex array.
+pub struct FullscreenTriangle {
+    vertex_array: u32
+}
 
-        unsafe {
-            gl::DrawElementsInstanced(
-                draw_mode,
-                count as gl::types::GLsizei,
-                gl::UNSIGNED_INT,
-                0 as *const _,
-                num_instances as gl::types::GLsizei
-            );
-        }
+impl FullscreenTriangle {
 
+    pub fn new() -> FullscreenTriangle {
+        return FullscreenTriangle {
+            vertex_array: backend::current().create_vertex_array()
+        };
     }
 
-    pub fn bind_texture(id: u32, bind_location: u32) {
-        unsafe {
-            gl::ActiveTexture(gl::TEXTURE0 + bind_location);
-            gl::BindTexture(gl::TEXTURE_2D, id);
-        }
+    pub fn draw(&self, api: &dyn Api) {
+        backend::current().bind_vertex_array(self.vertex_array);
+        api.draw_arrays(DrawMode::Triangles, 0, 3);
+        backend::current().unbind_vertex_array();
     }
 
-    pub fn unbind_texture(_id: u32) {
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+    pub fn free(&mut self) {
+        if self.vertex_array != 0 {
+            backend::current().delete_vertex_array(self.vertex_array);
+            self.vertex_array = 0;
         }
     }
 
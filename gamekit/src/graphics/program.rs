@@ -2,98 +2,136 @@
 // Program
 //
 
-use std::ffi::CString;
+use std::{cell::{Cell, Ref, RefCell}, collections::HashMap, fs, rc::Rc, time::SystemTime};
 
-use crate::graphics::{shader::Shader};
+use crate::graphics::{shader::{Shader, ShaderOptions, ShaderType}};
 
-use super::gl;
+use super::{backend, buffers::{BufferObject, BufferType}, error::GamekitError, program_cache, reflection::ProgramInterface, shader_watcher};
 
 defaults!();
 
-pub struct Program {
-    id: u32,
+thread_local! {
+    // counts deleted programs, their ids are reused for new programs
+    static DELETED_PROGRAMS: Cell<u64> = Cell::new(0);
 }
 
-impl Program {
+// changes whenever a program id may have been reused
+pub(crate) fn program_generation() -> u64 {
+    return DELETED_PROGRAMS.with(|count| count.get());
+}
 
-    pub fn new(shaders: &Vec<Shader>) -> Result<Program, String> {
+fn delete_program(id: u32) {
+    backend::current().delete_program(id);
+    DELETED_PROGRAMS.with(|count| count.set(count.get() + 1));
+}
 
-        let id = unsafe { gl::CreateProgram() };
+struct ShaderSource {
+    shader_type: ShaderType,
+    path: String,
+    options: ShaderOptions,
+    // the source and its includes with their modification times
+    files: Vec<(String, Option<SystemTime>)>
+}
 
-        unsafe {
-            for shader in shaders {
-                gl::AttachShader(id, shader.id());
-            }
-        }
+impl ShaderSource {
 
-        unsafe {
-            gl::LinkProgram(id);
-        }
+    fn new(shader: &Shader, path: &str) -> ShaderSource {
+        return ShaderSource {
+            shader_type: shader.shader_type(),
+            path: path.to_string(),
+            options: shader.options().clone(),
+            files: std::iter::once(path).chain(shader.include_files().iter().map(|file| file.as_str()))
+                .map(|file| (file.to_string(), modification_time(file)))
+                .collect()
+        };
+    }
 
-        let mut success: gl::types::GLint = 0;
-        unsafe {
-            gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
-        }
+    fn load(&self) -> Result<Shader, GamekitError> {
+        return Shader::with_options(self.shader_type, &self.path, &self.options);
+    }
 
-        if success == 0 {
-            let mut len: gl::types::GLint = 0;
-            unsafe {
-                gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
-            }
+}
 
-            let error = create_whitespace_cstring_with_len(len as usize);
+// shared with the shader watcher, the id changes when the program is reloaded
+pub(crate) struct ProgramState {
+    id: Cell<u32>,
+    // reflected at link time
+    interface: RefCell<ProgramInterface>,
+    // empty if a shader was generated at runtime and the program cannot be reloaded
+    sources: RefCell<Vec<ShaderSource>>
+}
 
-            unsafe {
-                gl::GetProgramInfoLog(
-                    id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar
-                );
-            }
+pub struct Program {
+    state: Rc<ProgramState>
+}
 
-            error!("failed to link program: {}", error.to_string_lossy());
+impl Program {
 
-            return Err(error.to_string_lossy().into_owned());
-        }
+    pub fn new(shaders: &Vec<Shader>) -> Result<Program, GamekitError> {
 
-        unsafe {
-            for shader in shaders {
-                gl::DetachShader(id, shader.id());
-            }
-        }
+        let (id, interface) = link_program(shaders)?;
+
+        let sources = shaders.iter()
+            .map(|shader| shader.source_path().map(|path| ShaderSource::new(shader, path)))
+            .collect::<Option<Vec<ShaderSource>>>()
+            .unwrap_or_default();
 
         let program = Program {
-            id
+            state: Rc::new(ProgramState {
+                id: Cell::new(id),
+                interface: RefCell::new(interface),
+                sources: RefCell::new(sources)
+            })
         };
 
+        if program.is_reloadable() {
+            shader_watcher::watch(&program.state);
+        }
+
         return Ok(program);
 
     }
 
     pub fn get_current_program() -> u32 {
-        let mut program_id: gl::types::GLint = 0;
+        return backend::current().current_program();
+    }
 
-        unsafe {
-            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program_id as *mut i32);
-        }
+    pub fn id(&self) -> u32 {
+        return self.state.id.get();
+    }
+
+    // active uniforms, blocks and vertex inputs, updated when the program is reloaded
+    pub fn interface(&self) -> Ref<'_, ProgramInterface> {
+        return self.state.interface.borrow();
+    }
 
-        return program_id as u32;
+    // location of an active uniform outside of blocks, arrays can be given without [0]
+    pub fn uniform_location(&self, name: &str) -> Option<i32> {
+        let interface = self.state.interface.borrow();
+        return interface.uniform(name)
+            .or_else(|| interface.uniform(&format!("{}[0]", name)))
+            .map(|uniform| uniform.location);
+    }
+
+    // true if all shaders were loaded from files
+    pub fn is_reloadable(&self) -> bool {
+        return !self.state.sources.borrow().is_empty();
+    }
+
+    // recompiles and relinks the shader files, the previous program is kept if that fails
+    pub fn reload(&self) -> Result<(), GamekitError> {
+        return self.state.reload();
     }
 
     pub fn use_program(&self) {
-        unsafe {
-            gl::UseProgram(self.id);
-        }
+        backend::current().use_program(self.state.id.get());
     }
 
     pub fn free(&mut self) {
         info!("Program free");
-        if self.id != 0 {
-            unsafe {
-                gl::DeleteProgram(self.id);
-            }
-            self.id = 0;
+        let id = self.state.id.replace(0);
+        if id != 0 {
+            delete_program(id);
         }
     }
 
@@ -106,11 +144,228 @@ impl Drop for Program {
     }
 }
 
-fn create_whitespace_cstring_with_len(len: usize) -> CString {
-    // allocate buffer of correct size
-    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
-    // fill it with len spaces
-    buffer.extend([b' '].iter().cycle().take(len));
-    // convert buffer to CString
-    unsafe { CString::from_vec_unchecked(buffer) }
+// Programs built from the same vertex and fragment shader files with different
+// define sets, each variant is compiled on first use:
+//
+//     let program = variants.get(&[("MAX_LIGHTS", "4"), ("USE_FOG", "1")])?;
+pub struct ProgramVariants {
+    vertex_shader: String,
+    fragment_shader: String,
+    variants: HashMap<Vec<(String, String)>, Program>
+}
+
+impl ProgramVariants {
+
+    pub fn new(vertex_shader: &str, fragment_shader: &str) -> ProgramVariants {
+        return ProgramVariants {
+            vertex_shader: vertex_shader.to_string(),
+            fragment_shader: fragment_shader.to_string(),
+            variants: HashMap::new()
+        };
+    }
+
+    pub fn get(&mut self, defines: &[(&str, &str)]) -> Result<&Program, GamekitError> {
+
+        // the define order does not matter
+        let mut key: Vec<(String, String)> = defines.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        key.sort();
+
+        if !self.variants.contains_key(&key) {
+            let shaders = vec!(
+                Shader::with_defines(ShaderType::VertexShader, &self.vertex_shader, defines)?,
+                Shader::with_defines(ShaderType::FragmentShader, &self.fragment_shader, defines)?
+            );
+            self.variants.insert(key.clone(), Program::new(&shaders)?);
+        }
+
+        return Ok(&self.variants[&key]);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.variants.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.variants.is_empty();
+    }
+
+}
+
+// Program with a single compute shader, writes to buffers become visible to
+// later draws after a matching barrier:
+//
+//     simulation.dispatch(particle_count.div_ceil(64), 1, 1);
+//     api.memory_barrier(&[MemoryBarrier::VertexAttribArray]);
+pub struct ComputeProgram {
+    program: Program
+}
+
+impl ComputeProgram {
+
+    pub fn new(filename: &str) -> Result<ComputeProgram, GamekitError> {
+        let shader = Shader::new(ShaderType::ComputeShader, filename)?;
+        return ComputeProgram::from_shader(shader);
+    }
+
+    pub fn from_shader(shader: Shader) -> Result<ComputeProgram, GamekitError> {
+
+        if shader.shader_type() != ShaderType::ComputeShader {
+            return Err(GamekitError::invalid_argument(format!("{} is not a compute shader", shader.filename())));
+        }
+
+        let compute_program = ComputeProgram {
+            program: Program::new(&vec!(shader))?
+        };
+
+        return Ok(compute_program);
+    }
+
+    // for setting uniforms
+    pub fn program(&self) -> &Program {
+        return &self.program;
+    }
+
+    // runs x * y * z work groups
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.program.use_program();
+        backend::current().dispatch_compute(x, y, z);
+    }
+
+    // reads the work group counts (three u32) at offset from the buffer,
+    // e.g. written by a previous dispatch
+    pub fn dispatch_indirect(&self, buffer: &BufferObject, offset: usize) {
+        self.program.use_program();
+        let backend = backend::current();
+        backend.bind_buffer(buffer.id(), &BufferType::DispatchIndirectBuffer);
+        backend.dispatch_compute_indirect(offset);
+        backend.unbind_buffer(buffer.id(), &BufferType::DispatchIndirectBuffer);
+    }
+
+}
+
+impl ProgramState {
+
+    // reloads the program if one of its shader files was modified since the last check
+    pub(crate) fn reload_if_modified(&self) {
+
+        let modified = {
+            let mut sources = self.sources.borrow_mut();
+            let mut modified = false;
+            for (file, modified_time) in sources.iter_mut().flat_map(|source| source.files.iter_mut()) {
+                let modification_time = modification_time(file);
+                if modification_time != *modified_time {
+                    *modified_time = modification_time;
+                    modified = true;
+                }
+            }
+            modified
+        };
+
+        if modified && self.id.get() != 0 {
+            info!("reloading program {}", self.id.get());
+            if self.reload().is_err() {
+                warn!("keeping previous version of program {}", self.id.get());
+            }
+        }
+    }
+
+    fn reload(&self) -> Result<(), GamekitError> {
+
+        let shaders = self.sources.borrow().iter()
+            .map(|source| source.load())
+            .collect::<Result<Vec<Shader>, GamekitError>>()?;
+
+        let (id, interface) = link_program(&shaders)?;
+
+        // includes may have changed
+        for (source, shader) in self.sources.borrow_mut().iter_mut().zip(shaders.iter()) {
+            *source = ShaderSource::new(shader, &source.path);
+        }
+
+        *self.interface.borrow_mut() = interface;
+
+        let previous_id = self.id.replace(id);
+        if previous_id != 0 {
+            let backend = backend::current();
+            if backend.current_program() == previous_id {
+                backend.use_program(id);
+            }
+            delete_program(previous_id);
+        }
+
+        return Ok(());
+    }
+
+}
+
+fn link_program(shaders: &Vec<Shader>) -> Result<(u32, ProgramInterface), GamekitError> {
+
+    if let Err(error) = validate_stages(shaders) {
+        error!("{}", error);
+        return Err(error);
+    }
+
+    let shader_ids: Vec<u32> = shaders.iter().map(|shader| shader.id()).collect();
+
+    let backend = backend::current();
+
+    let cache_key = program_cache::key(shaders);
+
+    let id = match cache_key.and_then(program_cache::load) {
+        Some(id) => id,
+        None => {
+            let id = match backend.create_program(&shader_ids) {
+                Ok(id) => id,
+                Err(error) => {
+                    error!("{}", error);
+                    return Err(error);
+                }
+            };
+            if let Some(cache_key) = cache_key {
+                program_cache::store(cache_key, id);
+            }
+            id
+        }
+    };
+
+    let interface = backend.program_interface(id);
+    debug!("program {} interface:\n{}", id, interface);
+
+    return Ok((id, interface));
+}
+
+// compute shaders are linked alone, tessellation and geometry stages need a vertex
+// shader and a tessellation control shader needs an evaluation shader to consume
+// its patches
+fn validate_stages(shaders: &[Shader]) -> Result<(), GamekitError> {
+
+    let has_stage = |shader_type: ShaderType| shaders.iter().any(|shader| shader.shader_type() == shader_type);
+    let names = || shaders.iter().map(|shader| shader.filename()).collect::<Vec<&str>>().join(", ");
+
+    if shaders.is_empty() {
+        return Err(GamekitError::invalid_argument("program without shaders"));
+    }
+
+    if has_stage(ShaderType::ComputeShader) {
+        if shaders.iter().any(|shader| shader.shader_type() != ShaderType::ComputeShader) {
+            return Err(GamekitError::invalid_argument(format!("compute shaders cannot be linked with other stages ({})", names())));
+        }
+        return Ok(());
+    }
+
+    let needs_vertex_shader = has_stage(ShaderType::TessControlShader) || has_stage(ShaderType::TessEvaluationShader) || has_stage(ShaderType::GeometryShader);
+
+    if needs_vertex_shader && !has_stage(ShaderType::VertexShader) {
+        return Err(GamekitError::invalid_argument(format!("tessellation and geometry shaders need a vertex shader ({})", names())));
+    }
+
+    if has_stage(ShaderType::TessControlShader) && !has_stage(ShaderType::TessEvaluationShader) {
+        return Err(GamekitError::invalid_argument(format!("tessellation control shader without evaluation shader ({})", names())));
+    }
+
+    return Ok(());
+}
+
+fn modification_time(path: &str) -> Option<SystemTime> {
+    return fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
 }
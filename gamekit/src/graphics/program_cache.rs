@@ -0,0 +1,133 @@
+//
+// Program Cache
+//
+
+// On-disk cache of linked programs. Program::new stores the program binary
+// (glGetProgramBinary) under a key built from the compiled shader code and the
+// driver, later runs load it (glProgramBinary) instead of linking. Binaries the
+// driver rejects, e.g. after an update, fall back to a full link and are
+// replaced. Disabled until a directory is set:
+//
+//     program_cache::set_directory(Some("target/program_cache"));
+
+use std::{cell::RefCell, fs, hash::{Hash, Hasher}, path::{Path, PathBuf}};
+
+use super::{backend, shader::Shader};
+
+defaults!();
+
+// part of every key, changing it invalidates all cached programs
+const CACHE_VERSION: u32 = 1;
+
+thread_local! {
+    static DIRECTORY: RefCell<Option<PathBuf>> = RefCell::new(None);
+}
+
+pub fn set_directory(directory: Option<&str>) {
+    DIRECTORY.with(|value| *value.borrow_mut() = directory.map(PathBuf::from));
+}
+
+pub fn directory() -> Option<PathBuf> {
+    return DIRECTORY.with(|value| value.borrow().clone());
+}
+
+// FNV-1a, unlike DefaultHasher the result is the same for every build
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub fn new() -> Fnv1a {
+        return Fnv1a(0xcbf29ce484222325);
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Fnv1a {
+        return Fnv1a::new();
+    }
+}
+
+impl Hasher for Fnv1a {
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        return self.0;
+    }
+
+}
+
+// None if the cache is disabled
+pub(crate) fn key(shaders: &[Shader]) -> Option<u64> {
+
+    directory()?;
+
+    let mut hasher = Fnv1a::new();
+    CACHE_VERSION.hash(&mut hasher);
+    backend::current().driver_id().hash(&mut hasher);
+
+    for shader in shaders {
+        shader.shader_type().hash(&mut hasher);
+        shader.code_hash().hash(&mut hasher);
+    }
+
+    return Some(hasher.finish());
+}
+
+// creates the program from the cached binary
+pub(crate) fn load(key: u64) -> Option<u32> {
+
+    let path = cache_file(key)?;
+
+    let data = fs::read(&path).ok()?;
+    if data.len() <= 4 {
+        return None;
+    }
+
+    let (format, binary) = data.split_at(4);
+    let format = u32::from_le_bytes([format[0], format[1], format[2], format[3]]);
+
+    return match backend::current().create_program_from_binary(format, binary) {
+        Ok(id) => {
+            debug!("loaded program {} from {}", id, path.display());
+            Some(id)
+        },
+        Err(error) => {
+            // drivers often give no reason
+            info!("cached program {} was rejected, linking", path.display());
+            debug!("{}", error);
+            None
+        }
+    };
+}
+
+pub(crate) fn store(key: u64, program: u32) {
+
+    let path = match cache_file(key) {
+        Some(path) => path,
+        None => { return; }
+    };
+
+    let (format, binary) = match backend::current().program_binary(program) {
+        Some(binary) => binary,
+        None => { return; }
+    };
+
+    let mut data = Vec::with_capacity(4 + binary.len());
+    data.extend_from_slice(&format.to_le_bytes());
+    data.extend_from_slice(&binary);
+
+    let result = fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))
+        .and_then(|_| fs::write(&path, &data));
+
+    if let Err(error) = result {
+        warn!("failed to write program cache {}: {}", path.display(), error);
+    }
+}
+
+fn cache_file(key: u64) -> Option<PathBuf> {
+    return directory().map(|directory| directory.join(format!("{:016x}.bin", key)));
+}
@@ -0,0 +1,431 @@
+//
+// Recording Backend
+//
+
+use std::{cell::{RefCell, Ref}, collections::HashMap};
+
+use super::{
+    api::{BlendMode, MemoryBarrier},
+    primitives::DrawMode,
+    buffers::{BufferType, BufferUsage},
+    error::GamekitError,
+    reflection::ProgramInterface,
+    shader::ShaderType,
+    backend::{Backend, HostMapping, TextureFormat, DepthStencilFormat, ShaderCode, VertexAttribute, UniformValue}
+};
+
+defaults!();
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Initialize,
+    Viewport { x: u32, y: u32, width: u32, height: u32 },
+    ClearColor { red: f32, green: f32, blue: f32, alpha: f32 },
+    Clear,
+    SetBlendMode(BlendMode),
+    ReadPixels { x: u32, y: u32, width: u32, height: u32, framebuffer_height: u32 },
+    DrawArrays { mode: DrawMode, ofs: usize, count: usize },
+    DrawElements { mode: DrawMode, count: usize },
+    DrawElementsInstanced { mode: DrawMode, count: usize, num_instances: usize },
+    DispatchCompute { x: u32, y: u32, z: u32 },
+    DispatchComputeIndirect { buffer: u32, offset: usize },
+    MemoryBarrier(Vec<MemoryBarrier>),
+    CreateBuffer { id: u32 },
+    DeleteBuffer { id: u32 },
+    BindBuffer { id: u32, buffer_type: BufferType },
+    BindBufferBase { id: u32, binding_point: u32, buffer_type: BufferType },
+    UnbindBuffer { buffer_type: BufferType },
+    BufferData { id: u32, buffer_usage: BufferUsage, data: Vec<u8> },
+    BufferSubData { id: u32, offset: usize, data: Vec<u8> },
+    CreateMappedBuffer { id: u32, size: usize },
+    FenceSync { fence: u64 },
+    WaitFence { fence: u64 },
+    DeleteFence { fence: u64 },
+    CreateVertexArray { id: u32 },
+    DeleteVertexArray { id: u32 },
+    BindVertexArray { id: u32 },
+    UnbindVertexArray,
+    VertexArrayAttribute { vertex_array: u32, binding: u32, attribute: VertexAttribute },
+    VertexArrayBuffer { vertex_array: u32, binding: u32, buffer: u32, offset: usize, stride: usize, divisor: u32 },
+    VertexArrayIndexBuffer { vertex_array: u32, buffer: u32 },
+    CreateTexture { id: u32, width: u32, height: u32, format: TextureFormat },
+    DeleteTexture { id: u32 },
+    BindTexture { id: u32, bind_location: u32 },
+    UnbindTexture { bind_location: u32 },
+    CreateRenderTexture { id: u32, width: u32, height: u32, format: TextureFormat },
+    CreateRenderbuffer { id: u32, width: u32, height: u32, format: DepthStencilFormat },
+    DeleteRenderbuffer { id: u32 },
+    CreateFramebuffer { id: u32, color_attachments: Vec<u32>, depth_stencil: Option<u32> },
+    DeleteFramebuffer { id: u32 },
+    BindFramebuffer { id: u32 },
+    ReadFramebuffer { id: u32, attachment: u32, width: u32, height: u32 },
+    CreateShader { id: u32, shader_type: ShaderType },
+    DeleteShader { id: u32 },
+    CreateProgram { id: u32, shaders: Vec<u32> },
+    DeleteProgram { id: u32 },
+    UseProgram { id: u32 },
+    SetUniform { program: u32, location: i32, value: UniformValue },
+    SetUniformArray { program: u32, location: i32, values: Vec<UniformValue> }
+}
+
+struct State {
+    commands: Vec<Command>,
+    next_id: u32,
+    bound_buffers: HashMap<BufferType, u32>,
+    // writes to mapped buffers are not recorded
+    mapped_buffers: HashMap<u32, HostMapping>,
+    current_program: u32,
+    uniform_locations: HashMap<(u32, String), i32>
+}
+
+// Backend without any device, every call is appended to an inspectable command log
+pub struct RecordingBackend {
+    state: RefCell<State>
+}
+
+impl RecordingBackend {
+
+    pub fn new() -> RecordingBackend {
+        let state = State {
+            commands: Vec::new(),
+            next_id: 1,
+            bound_buffers: HashMap::new(),
+            mapped_buffers: HashMap::new(),
+            current_program: 0,
+            uniform_locations: HashMap::new()
+        };
+
+        return RecordingBackend {
+            state: RefCell::new(state)
+        };
+    }
+
+    pub fn commands(&self) -> Ref<'_, Vec<Command>> {
+        return Ref::map(self.state.borrow(), |state| &state.commands);
+    }
+
+    pub fn take_commands(&self) -> Vec<Command> {
+        return std::mem::take(&mut self.state.borrow_mut().commands);
+    }
+
+    pub fn clear_commands(&self) {
+        self.state.borrow_mut().commands.clear();
+    }
+
+    pub fn draw_calls(&self) -> Vec<Command> {
+        return self.commands().iter().filter(|command| {
+            matches!(command,
+                Command::DrawArrays {..} |
+                Command::DrawElements {..} |
+                Command::DrawElementsInstanced {..})
+        }).cloned().collect();
+    }
+
+    fn record(&self, command: Command) {
+        trace!("{:?}", command);
+        self.state.borrow_mut().commands.push(command);
+    }
+
+    fn allocate_id(&self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id;
+        state.next_id += 1;
+        return id;
+    }
+
+    fn bound_buffer(&self, buffer_type: &BufferType) -> u32 {
+        return self.state.borrow().bound_buffers.get(buffer_type).copied().unwrap_or(0);
+    }
+
+}
+
+impl Backend for RecordingBackend {
+
+    fn initialize(&self) {
+        self.record(Command::Initialize);
+    }
+
+    fn viewport(&self, x: u32, y: u32, width: u32, height: u32) {
+        self.record(Command::Viewport { x, y, width, height });
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
+        self.record(Command::ClearColor { red, green, blue, alpha });
+    }
+
+    fn clear(&self) {
+        self.record(Command::Clear);
+    }
+
+    fn set_blend_mode(&self, blend_mode: BlendMode) {
+        self.record(Command::SetBlendMode(blend_mode));
+    }
+
+    fn read_pixels(&self, x: u32, y: u32, width: u32, height: u32, framebuffer_height: u32) -> Vec<u8> {
+        self.record(Command::ReadPixels { x, y, width, height, framebuffer_height });
+        return vec![0u8; width as usize * height as usize * 4];
+    }
+
+    fn draw_arrays(&self, mode: DrawMode, ofs: usize, count: usize) {
+        self.record(Command::DrawArrays { mode, ofs, count });
+    }
+
+    fn draw_elements(&self, mode: DrawMode, count: usize) {
+        self.record(Command::DrawElements { mode, count });
+    }
+
+    fn draw_elements_instanced(&self, mode: DrawMode, count: usize, num_instances: usize) {
+        self.record(Command::DrawElementsInstanced { mode, count, num_instances });
+    }
+
+    fn dispatch_compute(&self, x: u32, y: u32, z: u32) {
+        self.record(Command::DispatchCompute { x, y, z });
+    }
+
+    fn dispatch_compute_indirect(&self, offset: usize) {
+        let buffer = self.bound_buffer(&BufferType::DispatchIndirectBuffer);
+        self.record(Command::DispatchComputeIndirect { buffer, offset });
+    }
+
+    fn memory_barrier(&self, barriers: &[MemoryBarrier]) {
+        self.record(Command::MemoryBarrier(barriers.to_vec()));
+    }
+
+    fn create_buffer(&self) -> u32 {
+        let id = self.allocate_id();
+        self.record(Command::CreateBuffer { id });
+        return id;
+    }
+
+    fn delete_buffer(&self, id: u32) {
+        self.state.borrow_mut().mapped_buffers.remove(&id);
+        self.record(Command::DeleteBuffer { id });
+    }
+
+    fn bind_buffer(&self, id: u32, buffer_type: &BufferType) {
+        self.state.borrow_mut().bound_buffers.insert(*buffer_type, id);
+        self.record(Command::BindBuffer { id, buffer_type: *buffer_type });
+    }
+
+    fn bind_buffer_base(&self, id: u32, binding_point: u32, buffer_type: &BufferType) {
+        // like glBindBufferBase, this also binds the generic binding point
+        self.state.borrow_mut().bound_buffers.insert(*buffer_type, id);
+        self.record(Command::BindBufferBase { id, binding_point, buffer_type: *buffer_type });
+    }
+
+    fn unbind_buffer(&self, _id: u32, buffer_type: &BufferType) {
+        self.state.borrow_mut().bound_buffers.remove(buffer_type);
+        self.record(Command::UnbindBuffer { buffer_type: *buffer_type });
+    }
+
+    fn buffer_data(&self, id: u32, buffer_usage: &BufferUsage, data: &[u8]) {
+        self.record(Command::BufferData { id, buffer_usage: *buffer_usage, data: data.to_vec() });
+    }
+
+    fn buffer_sub_data(&self, id: u32, offset: usize, data: &[u8]) {
+        self.record(Command::BufferSubData { id, offset, data: data.to_vec() });
+    }
+
+    fn create_mapped_buffer(&self, size: usize) -> Result<(u32, *mut u8), GamekitError> {
+        let id = self.allocate_id();
+        let mapping = HostMapping::new(size);
+        let data = mapping.as_ptr();
+        self.state.borrow_mut().mapped_buffers.insert(id, mapping);
+        self.record(Command::CreateMappedBuffer { id, size });
+        return Ok((id, data));
+    }
+
+    fn fence_sync(&self) -> u64 {
+        let fence = self.allocate_id() as u64;
+        self.record(Command::FenceSync { fence });
+        return fence;
+    }
+
+    fn wait_fence(&self, fence: u64) {
+        self.record(Command::WaitFence { fence });
+    }
+
+    fn delete_fence(&self, fence: u64) {
+        self.record(Command::DeleteFence { fence });
+    }
+
+    fn create_vertex_array(&self) -> u32 {
+        let id = self.allocate_id();
+        self.record(Command::CreateVertexArray { id });
+        return id;
+    }
+
+    fn delete_vertex_array(&self, id: u32) {
+        self.record(Command::DeleteVertexArray { id });
+    }
+
+    fn bind_vertex_array(&self, id: u32) {
+        self.record(Command::BindVertexArray { id });
+    }
+
+    fn unbind_vertex_array(&self) {
+        self.record(Command::UnbindVertexArray);
+    }
+
+    fn vertex_array_attribute(&self, vertex_array: u32, binding: u32, attribute: &VertexAttribute) {
+        self.record(Command::VertexArrayAttribute { vertex_array, binding, attribute: *attribute });
+    }
+
+    fn vertex_array_buffer(&self, vertex_array: u32, binding: u32, buffer: u32, offset: usize, stride: usize, divisor: u32) {
+        self.record(Command::VertexArrayBuffer { vertex_array, binding, buffer, offset, stride, divisor });
+    }
+
+    fn vertex_array_index_buffer(&self, vertex_array: u32, buffer: u32) {
+        self.record(Command::VertexArrayIndexBuffer { vertex_array, buffer });
+    }
+
+    fn create_texture(&self, width: u32, height: u32, format: TextureFormat, _pixels: &[u8]) -> Result<u32, GamekitError> {
+        let id = self.allocate_id();
+        self.record(Command::CreateTexture { id, width, height, format });
+        return Ok(id);
+    }
+
+    fn delete_texture(&self, id: u32) {
+        self.record(Command::DeleteTexture { id });
+    }
+
+    fn bind_texture(&self, id: u32, bind_location: u32) {
+        self.record(Command::BindTexture { id, bind_location });
+    }
+
+    fn unbind_texture(&self, bind_location: u32) {
+        self.record(Command::UnbindTexture { bind_location });
+    }
+
+    fn create_render_texture(&self, width: u32, height: u32, format: TextureFormat) -> Result<u32, GamekitError> {
+        let id = self.allocate_id();
+        self.record(Command::CreateRenderTexture { id, width, height, format });
+        return Ok(id);
+    }
+
+    fn create_renderbuffer(&self, width: u32, height: u32, format: DepthStencilFormat) -> Result<u32, GamekitError> {
+        let id = self.allocate_id();
+        self.record(Command::CreateRenderbuffer { id, width, height, format });
+        return Ok(id);
+    }
+
+    fn delete_renderbuffer(&self, id: u32) {
+        self.record(Command::DeleteRenderbuffer { id });
+    }
+
+    fn create_framebuffer(&self, color_attachments: &[u32], depth_stencil: Option<(u32, DepthStencilFormat)>) -> Result<u32, GamekitError> {
+        let id = self.allocate_id();
+        self.record(Command::CreateFramebuffer {
+            id,
+            color_attachments: color_attachments.to_vec(),
+            depth_stencil: depth_stencil.map(|(renderbuffer, _)| renderbuffer)
+        });
+        return Ok(id);
+    }
+
+    fn delete_framebuffer(&self, id: u32) {
+        self.record(Command::DeleteFramebuffer { id });
+    }
+
+    fn bind_framebuffer(&self, id: u32) {
+        self.record(Command::BindFramebuffer { id });
+    }
+
+    fn read_framebuffer(&self, id: u32, attachment: u32, width: u32, height: u32) -> Vec<u8> {
+        self.record(Command::ReadFramebuffer { id, attachment, width, height });
+        return vec![0u8; width as usize * height as usize * 4];
+    }
+
+    fn supports_spirv(&self) -> bool {
+        return true;
+    }
+
+    fn create_shader(&self, shader_type: ShaderType, _code: ShaderCode) -> Result<u32, GamekitError> {
+        let id = self.allocate_id();
+        self.record(Command::CreateShader { id, shader_type });
+        return Ok(id);
+    }
+
+    fn delete_shader(&self, id: u32) {
+        self.record(Command::DeleteShader { id });
+    }
+
+    fn create_program(&self, shaders: &[u32]) -> Result<u32, GamekitError> {
+        let id = self.allocate_id();
+        self.record(Command::CreateProgram { id, shaders: shaders.to_vec() });
+        return Ok(id);
+    }
+
+    fn program_binary(&self, _program: u32) -> Option<(u32, Vec<u8>)> {
+        return None;
+    }
+
+    fn create_program_from_binary(&self, _format: u32, _binary: &[u8]) -> Result<u32, GamekitError> {
+        return Err(GamekitError::invalid_argument("program binaries are not supported by the recording backend"));
+    }
+
+    fn driver_id(&self) -> String {
+        return "recording".to_string();
+    }
+
+    fn delete_program(&self, id: u32) {
+        self.record(Command::DeleteProgram { id });
+    }
+
+    fn use_program(&self, id: u32) {
+        self.state.borrow_mut().current_program = id;
+        self.record(Command::UseProgram { id });
+    }
+
+    fn current_program(&self) -> u32 {
+        return self.state.borrow().current_program;
+    }
+
+    fn program_interface(&self, _program: u32) -> ProgramInterface {
+        return ProgramInterface::default();
+    }
+
+    fn get_uniform_location(&self, program: u32, name: &str) -> i32 {
+        let mut state = self.state.borrow_mut();
+        let next_location = state.uniform_locations.len() as i32;
+        return *state.uniform_locations.entry((program, name.to_string())).or_insert(next_location);
+    }
+
+    fn set_uniform(&self, program: u32, location: i32, value: UniformValue) {
+        self.record(Command::SetUniform { program, location, value });
+    }
+
+    fn set_uniform_array(&self, program: u32, location: i32, values: &[UniformValue]) {
+        if !UniformValue::is_homogeneous(values) {
+            warn!("ignoring uniform array with mixed or no element types");
+            return;
+        }
+        self.record(Command::SetUniformArray { program, location, values: values.to_vec() });
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecordingBackend, Command};
+    use crate::graphics::{backend::{Backend, UniformValue}, types::Vector4};
+
+    #[test]
+    fn uniform_arrays_of_one_kind_are_recorded() {
+        let backend = RecordingBackend::new();
+        let values = [UniformValue::Vec4(Vector4::zero()), UniformValue::Vec4(Vector4::new(1.0, 2.0, 3.0, 4.0))];
+        backend.set_uniform_array(1, 2, &values);
+        assert_eq!(*backend.commands(), vec![Command::SetUniformArray { program: 1, location: 2, values: values.to_vec() }]);
+    }
+
+    #[test]
+    fn mixed_and_empty_uniform_arrays_are_ignored() {
+        let backend = RecordingBackend::new();
+        backend.set_uniform_array(1, 2, &[UniformValue::Vec4(Vector4::zero()), UniformValue::Float(1.0)]);
+        backend.set_uniform_array(1, 2, &[UniformValue::Float(1.0), UniformValue::Int(1)]);
+        backend.set_uniform_array(1, 2, &[UniformValue::Int(1), UniformValue::Bool(true)]);
+        backend.set_uniform_array(1, 2, &[]);
+        assert!(backend.commands().is_empty());
+    }
+
+}
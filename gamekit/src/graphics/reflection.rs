@@ -0,0 +1,292 @@
+//
+// Program Reflection
+//
+
+use std::fmt;
+
+use super::{error::GamekitError, layout::{BlockLayout, Layout}};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    UVec2,
+    UVec3,
+    UVec4,
+    Bool,
+    BVec2,
+    BVec3,
+    BVec4,
+    Mat2,
+    Mat3,
+    Mat4,
+    Mat2x3,
+    Mat2x4,
+    Mat3x2,
+    Mat3x4,
+    Mat4x2,
+    Mat4x3,
+    Sampler2D,
+    Sampler3D,
+    SamplerCube,
+    Sampler2DArray,
+    Sampler2DShadow,
+    Image2D,
+    // backend specific type id
+    Other(u32)
+}
+
+impl DataType {
+
+    // texture and image units are bound through the uniform value
+    pub fn is_opaque(&self) -> bool {
+        return matches!(self,
+            DataType::Sampler2D |
+            DataType::Sampler3D |
+            DataType::SamplerCube |
+            DataType::Sampler2DArray |
+            DataType::Sampler2DShadow |
+            DataType::Image2D);
+    }
+
+    // columns and rows of numeric types, vectors have one column
+    pub fn dimensions(&self) -> Option<(usize, usize)> {
+        return match self {
+            DataType::Float | DataType::Int | DataType::UInt | DataType::Bool => Some((1, 1)),
+            DataType::Vec2 | DataType::IVec2 | DataType::UVec2 | DataType::BVec2 => Some((1, 2)),
+            DataType::Vec3 | DataType::IVec3 | DataType::UVec3 | DataType::BVec3 => Some((1, 3)),
+            DataType::Vec4 | DataType::IVec4 | DataType::UVec4 | DataType::BVec4 => Some((1, 4)),
+            DataType::Mat2 => Some((2, 2)),
+            DataType::Mat3 => Some((3, 3)),
+            DataType::Mat4 => Some((4, 4)),
+            DataType::Mat2x3 => Some((2, 3)),
+            DataType::Mat2x4 => Some((2, 4)),
+            DataType::Mat3x2 => Some((3, 2)),
+            DataType::Mat3x4 => Some((3, 4)),
+            DataType::Mat4x2 => Some((4, 2)),
+            DataType::Mat4x3 => Some((4, 3)),
+            _ => None
+        };
+    }
+
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataType::Float => "float",
+            DataType::Vec2 => "vec2",
+            DataType::Vec3 => "vec3",
+            DataType::Vec4 => "vec4",
+            DataType::Int => "int",
+            DataType::IVec2 => "ivec2",
+            DataType::IVec3 => "ivec3",
+            DataType::IVec4 => "ivec4",
+            DataType::UInt => "uint",
+            DataType::UVec2 => "uvec2",
+            DataType::UVec3 => "uvec3",
+            DataType::UVec4 => "uvec4",
+            DataType::Bool => "bool",
+            DataType::BVec2 => "bvec2",
+            DataType::BVec3 => "bvec3",
+            DataType::BVec4 => "bvec4",
+            DataType::Mat2 => "mat2",
+            DataType::Mat3 => "mat3",
+            DataType::Mat4 => "mat4",
+            DataType::Mat2x3 => "mat2x3",
+            DataType::Mat2x4 => "mat2x4",
+            DataType::Mat3x2 => "mat3x2",
+            DataType::Mat3x4 => "mat3x4",
+            DataType::Mat4x2 => "mat4x2",
+            DataType::Mat4x3 => "mat4x3",
+            DataType::Sampler2D => "sampler2D",
+            DataType::Sampler3D => "sampler3D",
+            DataType::SamplerCube => "samplerCube",
+            DataType::Sampler2DArray => "sampler2DArray",
+            DataType::Sampler2DShadow => "sampler2DShadow",
+            DataType::Image2D => "image2D",
+            DataType::Other(id) => { return write!(f, "type 0x{:x}", id); }
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+// uniform outside of blocks
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniformInfo {
+    pub name: String,
+    pub data_type: DataType,
+    pub array_size: u32,
+    pub location: i32,
+    // texture or image unit of samplers and images
+    pub binding: Option<u32>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockMember {
+    pub name: String,
+    pub data_type: DataType,
+    pub array_size: u32,
+    pub offset: usize,
+    pub array_stride: usize,
+    pub matrix_stride: usize
+}
+
+impl BlockMember {
+
+    // bytes from the offset to the end of the last element, runtime sized arrays count as empty
+    pub fn size(&self) -> usize {
+
+        let element_size = match self.data_type.dimensions() {
+            Some((1, rows)) => rows * 4,
+            Some((columns, rows)) => if self.matrix_stride > 0 { columns * self.matrix_stride } else { columns * rows * 4 },
+            None => 0
+        };
+
+        return match self.array_size {
+            0 => 0,
+            1 => element_size,
+            array_size => (array_size as usize - 1) * self.array_stride + element_size
+        };
+    }
+
+}
+
+// uniform block or shader storage block
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockInfo {
+    pub name: String,
+    pub binding: u32,
+    pub data_size: usize,
+    pub members: Vec<BlockMember>
+}
+
+impl BlockInfo {
+
+    // members of blocks with an instance name are reported as "block.member",
+    // both forms are accepted
+    pub fn member(&self, name: &str) -> Option<&BlockMember> {
+        return self.members.iter().find(|member| {
+            member.name == name || member.name.strip_suffix(name).map(|prefix| prefix.ends_with('.')).unwrap_or(false)
+        });
+    }
+
+    // bytes up to the end of the last member, data_size includes the padding of the layout
+    pub fn used_size(&self) -> usize {
+        return self.members.iter().map(|member| member.offset + member.size()).max().unwrap_or(0);
+    }
+
+    // compares the layout of a CPU struct with the block:
+    //
+    //     block.validate(mem::size_of::<ShaderData>(), &[("time", mem::offset_of!(ShaderData, time))])?;
+    pub fn validate(&self, size: usize, offsets: &[(&str, usize)]) -> Result<(), GamekitError> {
+
+        if size < self.used_size() {
+            return Err(GamekitError::InterfaceMismatch(format!("block {} needs {} bytes, the struct has {}", self.name, self.used_size(), size)));
+        }
+
+        for (name, offset) in offsets {
+            match self.member(name) {
+                Some(member) if member.offset == *offset => {},
+                Some(member) => {
+                    return Err(GamekitError::InterfaceMismatch(format!("{}.{} is at offset {} in the shader and {} in the struct", self.name, name, member.offset, offset)));
+                },
+                None => {
+                    return Err(GamekitError::InterfaceMismatch(format!("block {} has no member {}", self.name, name)));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    // validate for derived BlockLayout structs, all members are compared
+    pub fn validate_layout<T: BlockLayout>(&self, layout: Layout) -> Result<(), GamekitError> {
+        let members = T::members(layout);
+        let offsets: Vec<(&str, usize)> = members.iter().map(|(name, offset)| (name.as_str(), *offset)).collect();
+        return self.validate(layout.of::<T>().size, &offsets);
+    }
+
+}
+
+// vertex shader input
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeInfo {
+    pub name: String,
+    pub data_type: DataType,
+    pub array_size: u32,
+    pub location: i32
+}
+
+// Active interface of a linked program, inactive (optimized out) variables
+// are not listed. Backends without shaders report an empty interface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgramInterface {
+    pub uniforms: Vec<UniformInfo>,
+    pub uniform_blocks: Vec<BlockInfo>,
+    pub storage_blocks: Vec<BlockInfo>,
+    pub attributes: Vec<AttributeInfo>
+}
+
+impl ProgramInterface {
+
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        return self.uniforms.iter().find(|uniform| uniform.name == name);
+    }
+
+    pub fn uniform_block(&self, name: &str) -> Option<&BlockInfo> {
+        return self.uniform_blocks.iter().find(|block| block.name == name);
+    }
+
+    pub fn storage_block(&self, name: &str) -> Option<&BlockInfo> {
+        return self.storage_blocks.iter().find(|block| block.name == name);
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        return self.attributes.iter().find(|attribute| attribute.name == name);
+    }
+
+}
+
+impl fmt::Display for ProgramInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        for attribute in &self.attributes {
+            writeln!(f, "in {}{} {} (location {})", attribute.data_type, array_suffix(attribute.array_size), attribute.name, attribute.location)?;
+        }
+
+        for uniform in &self.uniforms {
+            match uniform.binding {
+                Some(binding) => writeln!(f, "uniform {}{} {} (location {}, binding {})", uniform.data_type, array_suffix(uniform.array_size), uniform.name, uniform.location, binding)?,
+                None => writeln!(f, "uniform {}{} {} (location {})", uniform.data_type, array_suffix(uniform.array_size), uniform.name, uniform.location)?
+            }
+        }
+
+        for (kind, blocks) in [("uniform", &self.uniform_blocks), ("buffer", &self.storage_blocks)] {
+            for block in blocks {
+                writeln!(f, "{} {} (binding {}, {} bytes)", kind, block.name, block.binding, block.data_size)?;
+                for member in &block.members {
+                    writeln!(f, "    {}{} {} (offset {})", member.data_type, array_suffix(member.array_size), member.name, member.offset)?;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+// runtime sized arrays have size 0
+fn array_suffix(array_size: u32) -> String {
+    return match array_size {
+        0 => "[]".to_string(),
+        1 => String::new(),
+        _ => format!("[{}]", array_size)
+    };
+}
@@ -0,0 +1,141 @@
+//
+// Render Target
+//
+
+use super::{
+    texture::Texture,
+    image::Image,
+    error::GamekitError,
+    types::Rectangle,
+    backend::{self, TextureFormat, DepthStencilFormat}
+};
+
+defaults!();
+
+// Framebuffer object with color texture attachments and an optional
+// depth/stencil renderbuffer. The attachments are ordinary textures and can
+// be sampled in a later pass. Like every GL framebuffer, rows are stored
+// bottom-up: sprites use texture_coords() (SpriteBatch::with_render_target
+// does so), fullscreen passes sample in GL orientation and need no flip.
+pub struct RenderTarget {
+    id: u32,
+    width: u32,
+    height: u32,
+    color_attachments: Vec<Texture>,
+    depth_stencil: u32
+}
+
+impl RenderTarget {
+
+    pub fn new(width: u32, height: u32, color_formats: &[TextureFormat], depth_stencil_format: Option<DepthStencilFormat>) -> Result<RenderTarget, GamekitError> {
+
+        if color_formats.is_empty() {
+            return Err(GamekitError::invalid_argument("render target needs at least one color attachment"));
+        }
+
+        let mut color_attachments = Vec::with_capacity(color_formats.len());
+        for format in color_formats {
+            color_attachments.push(Texture::with_size(width, height, *format)?);
+        }
+
+        let backend = backend::current();
+
+        let depth_stencil = match depth_stencil_format {
+            Some(format) => Some((backend.create_renderbuffer(width, height, format)?, format)),
+            None => None
+        };
+
+        let texture_ids: Vec<u32> = color_attachments.iter().map(|texture| texture.id()).collect();
+
+        let id = match backend.create_framebuffer(&texture_ids, depth_stencil) {
+            Ok(id) => id,
+            Err(err) => {
+                if let Some((renderbuffer, _)) = depth_stencil {
+                    backend.delete_renderbuffer(renderbuffer);
+                }
+                return Err(err);
+            }
+        };
+
+        debug!("render target {}x{} with {} color attachments", width, height, color_attachments.len());
+
+        let render_target = RenderTarget {
+            id,
+            width,
+            height,
+            color_attachments,
+            depth_stencil: depth_stencil.map(|(renderbuffer, _)| renderbuffer).unwrap_or(0)
+        };
+
+        return Ok(render_target);
+    }
+
+    // single RGBA8 color attachment without depth/stencil
+    pub fn with_size(width: u32, height: u32) -> Result<RenderTarget, GamekitError> {
+        return Self::new(width, height, &[TextureFormat::Rgba8], None);
+    }
+
+    pub fn id(&self) -> u32 {
+        return self.id;
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    // color attachment 0
+    pub fn texture(&self) -> &Texture {
+        return &self.color_attachments[0];
+    }
+
+    pub fn color_attachment(&self, index: usize) -> Option<&Texture> {
+        return self.color_attachments.get(index);
+    }
+
+    pub fn color_attachments(&self) -> &[Texture] {
+        return &self.color_attachments;
+    }
+
+    // texture coordinates of the whole target with v = 0 at the top, for
+    // sprites and quads drawn with the default (top-left origin) projection
+    pub fn texture_coords(&self) -> Rectangle {
+        return Rectangle::new(0.0, 1.0, 1.0, -1.0);
+    }
+
+    // makes this the draw destination and covers it with the viewport,
+    // use Api::set_render_target(None) to return to the default framebuffer
+    pub fn bind(&self) {
+        let backend = backend::current();
+        backend.bind_framebuffer(self.id);
+        backend.viewport(0, 0, self.width, self.height);
+    }
+
+    pub fn read_image(&self, attachment: usize) -> Result<Image, GamekitError> {
+        let pixels = backend::current().read_framebuffer(self.id, attachment as u32, self.width, self.height);
+        return Image::new(self.width, self.height, pixels);
+    }
+
+    pub fn free(&mut self) {
+        info!("RenderTarget free");
+        if self.id != 0 {
+            let backend = backend::current();
+            backend.delete_framebuffer(self.id);
+            backend.delete_renderbuffer(self.depth_stencil);
+            self.id = 0;
+            self.depth_stencil = 0;
+        }
+        self.color_attachments.clear();
+    }
+
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        debug!("drop render target");
+        self.free();
+    }
+}
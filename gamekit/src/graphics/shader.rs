@@ -4,45 +4,157 @@
 
 defaults!();
 
-use std::ffi::c_void;
 use std::path::Path;
-use std::{ffi::{CString}, fs};
+use std::fs;
 
-use crate::graphics::gl;
+use super::{backend::{self, ShaderCode}, error::GamekitError, preprocessor, shader_compiler};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ShaderType {
     VertexShader,
-    FragmentShader
+    TessControlShader,
+    TessEvaluationShader,
+    GeometryShader,
+    FragmentShader,
+    ComputeShader
 }
 
 pub struct Shader {
     id: u32,
-    _shader_type: ShaderType,
-    _filename: String
+    shader_type: ShaderType,
+    filename: String,
+    // file the shader can be rebuilt from, the GLSL source next to a SPIR-V binary if there is one
+    source_path: Option<String>,
+    options: ShaderOptions,
+    // identifies the compiled code for the program binary cache
+    code_hash: u64,
+    // files pulled in by #include
+    include_files: Vec<String>
+}
+
+// how a shader file is compiled, kept for reloading
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ShaderOptions {
+    pub defines: Vec<(String, String)>,
+    pub entry_point: String,
+    // (constant id, value), floats are passed as their bits
+    pub constants: Vec<(u32, u32)>
+}
+
+impl Default for ShaderOptions {
+    fn default() -> ShaderOptions {
+        return ShaderOptions {
+            defines: Vec::new(),
+            entry_point: "main".to_string(),
+            constants: Vec::new()
+        };
+    }
 }
 
 impl Shader {
 
-    pub fn new(shader_type: ShaderType, filename: &str) -> Result<Shader, String> {
-        return load_shader(shader_type, filename);
+    pub fn new(shader_type: ShaderType, filename: &str) -> Result<Shader, GamekitError> {
+        return load_shader(shader_type, filename, ShaderOptions::default());
+    }
+
+    // compiles the GLSL source with #define name value lines injected after #version,
+    // SPIR-V binaries are replaced by their source
+    pub fn with_defines(shader_type: ShaderType, filename: &str, defines: &[(&str, &str)]) -> Result<Shader, GamekitError> {
+        let options = ShaderOptions {
+            defines: defines.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            ..ShaderOptions::default()
+        };
+        return load_shader(shader_type, filename, options);
+    }
+
+    // specializes a SPIR-V shader, constants are (constant id, value) pairs with floats as
+    // f32::to_bits and bools as 0 or 1. GLSL sources compiled by the driver get the values
+    // of their layout(constant_id = N) constants replaced and always start at main.
+    pub fn with_specialization(shader_type: ShaderType, filename: &str, entry_point: &str, constants: &[(u32, u32)]) -> Result<Shader, GamekitError> {
+        let options = ShaderOptions {
+            entry_point: entry_point.to_string(),
+            constants: constants.to_vec(),
+            ..ShaderOptions::default()
+        };
+        return load_shader(shader_type, filename, options);
+    }
+
+    pub(crate) fn with_options(shader_type: ShaderType, filename: &str, options: &ShaderOptions) -> Result<Shader, GamekitError> {
+        return load_shader(shader_type, filename, options.clone());
+    }
+
+    // compiles GLSL source generated at runtime, the name is used for diagnostics
+    pub fn from_source(shader_type: ShaderType, source: &str, name: &str) -> Result<Shader, GamekitError> {
+
+        let code = ShaderCode::Source(source);
+
+        let id = match backend::current().create_shader(shader_type, code.clone()) {
+            Ok(id) => id,
+            Err(error) => {
+                let error = error.with_shader_file(name);
+                error!("{}", error);
+                return Err(error);
+            }
+        };
+
+        let shader = Shader {
+            id,
+            shader_type,
+            filename: name.to_string(),
+            source_path: None,
+            options: ShaderOptions::default(),
+            code_hash: code.stable_hash(),
+            include_files: Vec::new()
+        };
+
+        return Ok(shader);
     }
 
-    #[allow(dead_code)]
     pub fn shader_type(&self) -> ShaderType {
-        return self._shader_type;
+        return self.shader_type;
+    }
+
+    pub fn filename(&self) -> &str {
+        return &self.filename;
+    }
+
+    // None for shaders generated at runtime
+    pub fn source_path(&self) -> Option<&str> {
+        return self.source_path.as_deref();
+    }
+
+    pub fn defines(&self) -> &[(String, String)] {
+        return &self.options.defines;
+    }
+
+    pub fn entry_point(&self) -> &str {
+        return &self.options.entry_point;
+    }
+
+    pub fn specialization_constants(&self) -> &[(u32, u32)] {
+        return &self.options.constants;
+    }
+
+    pub(crate) fn options(&self) -> &ShaderOptions {
+        return &self.options;
+    }
+
+    pub fn include_files(&self) -> &[String] {
+        return &self.include_files;
     }
 
     pub fn id(&self) -> u32 {
         return self.id;
     }
 
+    pub fn code_hash(&self) -> u64 {
+        return self.code_hash;
+    }
+
     pub fn free(&mut self) {
         info!("Shader free");
         if self.id != 0 {
-            unsafe {
-                gl::DeleteShader(self.id);
-            }
+            backend::current().delete_shader(self.id);
             self.id = 0;
         }
     }
@@ -56,112 +168,94 @@ impl Drop for Shader {
     }
 }
 
-fn create_whitespace_cstring_with_len(len: usize) -> CString {
-    // allocate buffer of correct size
-    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
-    // fill it with len spaces
-    buffer.extend([b' '].iter().cycle().take(len));
-    // convert buffer to CString
-    unsafe { CString::from_vec_unchecked(buffer) }
-}
+fn load_shader(shader_type: ShaderType, filename: &str, options: ShaderOptions) -> Result<Shader, GamekitError> {
 
-fn load_shader(shader_type: ShaderType, filename: &str) -> Result<Shader, String> {
+    let (id, code_hash, include_files) = match create_shader(shader_type, filename, &options) {
+        Ok(result) => result,
+        Err(error) => {
+            let error = error.with_shader_file(filename);
+            error!("{}", error);
+            return Err(error);
+        }
+    };
 
-    let kind: gl::types::GLuint;
+    let shader = Shader {
+        id,
+        shader_type,
+        filename: filename.to_string(),
+        source_path: Some(source_path(filename)),
+        options,
+        code_hash,
+        include_files
+    };
 
-    match shader_type {
-        ShaderType::VertexShader => { kind = gl::VERTEX_SHADER; },
-        ShaderType::FragmentShader => { kind = gl::FRAGMENT_SHADER; }
-    }
+    return Ok(shader);
 
-    let file_ext = Path::new(&filename).extension();
-    if file_ext.is_none() {
-        return Err(format!("failed to access shader file {}", filename));
+}
+
+fn source_path(filename: &str) -> String {
+    if let Some(source_filename) = filename.strip_suffix(".spv") {
+        if Path::new(source_filename).exists() {
+            return source_filename.to_string();
+        }
     }
+    return filename.to_string();
+}
 
-    let file_ext = file_ext.unwrap();
+// SPIR-V binaries fall back to the GLSL source next to them (shader.vert for shader.vert.spv)
+// when the backend cannot ingest SPIR-V or the binary was not built. GLSL sources are compiled
+// to SPIR-V in-process if possible, otherwise passed to the driver (glShaderSource).
+// Returns the shader, the hash of the code passed to the backend and the files included by the source.
+fn create_shader(shader_type: ShaderType, filename: &str, options: &ShaderOptions) -> Result<(u32, u64, Vec<String>), GamekitError> {
 
-    let is_binary = if file_ext.eq_ignore_ascii_case("spv") { true } else { false };
+    let is_binary = match Path::new(&filename).extension() {
+        Some(file_ext) => file_ext.eq_ignore_ascii_case("spv"),
+        None => { return Err(GamekitError::invalid_argument(format!("missing extension of shader file {}", filename))); }
+    };
 
-    let id = unsafe { gl::CreateShader(kind) };
+    let backend = backend::current();
 
     if is_binary {
 
-        let shader_binary = fs::read(filename);
-        let mut shader_binary = match shader_binary {
-            Ok(binary) => binary,
-            Err(_) => {
-                unsafe { gl::DeleteShader(id); }
-                return Err(format!("failed to read shader file {}", filename));
-            }
-        };
-
-        let shader_binary_size = shader_binary.len();
-
-        let entry_point = &CString::new("main").unwrap();
-        let constant_index = 0;
-        let constant_value = 0;
+        let source_filename = &filename[..filename.len() - ".spv".len()];
 
-        unsafe {
-            gl::ShaderBinary(1, &id, gl::SHADER_BINARY_FORMAT_SPIR_V, shader_binary.as_mut_ptr() as *mut c_void, shader_binary_size as gl::types::GLsizei);
-            gl::SpecializeShader(id, entry_point.as_ptr(), 0, &constant_index, &constant_value);
+        if !backend.supports_spirv() || !options.defines.is_empty() || (!Path::new(filename).exists() && Path::new(source_filename).exists()) {
+            debug!("loading shader source {} instead of {}", source_filename, filename);
+            return create_shader(shader_type, source_filename, options);
         }
 
-    } else {
-
-        let shader_source =fs::read_to_string(filename);
-        let shader_source = match shader_source {
-            Ok(source) => source,
-            Err(_) => {
-                unsafe { gl::DeleteShader(id); }
-                return Err(format!("failed to read shader file {}", filename));
+        let shader_binary = match fs::read(filename) {
+            Ok(binary) => binary,
+            Err(err) => {
+                return Err(GamekitError::io(filename, err));
             }
         };
 
-        let source = &CString::new(shader_source).unwrap();
-
-        unsafe {
-            gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
-            gl::CompileShader(id);
-        }
-
-    }
-
-    let mut success: gl::types::GLint = 0;
-    unsafe {
-        gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+        let code = ShaderCode::Binary(&shader_binary, &options.entry_point, &options.constants);
+        let id = backend.create_shader(shader_type, code.clone())?;
+        return Ok((id, code.stable_hash(), Vec::new()));
     }
 
-    if success == 0 {
-        let mut len: gl::types::GLint = 0;
-        unsafe {
-            gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
-        }
-
-        let error = create_whitespace_cstring_with_len(len as usize);
+    let defines: Vec<(&str, &str)> = options.defines.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+    let preprocessed = preprocessor::preprocess(filename, &defines)?;
 
-        unsafe {
-            gl::GetShaderInfoLog(
-                id,
-                len,
-                std::ptr::null_mut(),
-                error.as_ptr() as *mut gl::types::GLchar
-            );
-
-            gl::DeleteShader(id);
-        }
-
-        error!("failed to compile shader: {}", error.to_string_lossy());
+    let map_error = |error| match error {
+        GamekitError::ShaderCompile { file, log } => GamekitError::ShaderCompile { file, log: preprocessed.map_log(&log) },
+        error => error
+    };
 
-        return Err(error.to_string_lossy().into_owned());
-    }
+    let shader_binary;
+    let source;
 
-    let shader = Shader {
-        id,
-        _shader_type: shader_type,
-        _filename: filename.to_string()
+    let code = if backend.supports_spirv() && shader_compiler::is_available() {
+        shader_binary = shader_compiler::compile_to_spirv(shader_type, &preprocessed.source, filename, &options.entry_point).map_err(map_error)?;
+        ShaderCode::Binary(&shader_binary, &options.entry_point, &options.constants)
+    } else {
+        source = preprocessor::specialize(&preprocessed.source, &options.constants);
+        ShaderCode::Source(&source)
     };
 
-    return Ok(shader);
+    let id = backend.create_shader(shader_type, code.clone()).map_err(map_error)?;
 
+    return Ok((id, code.stable_hash(), preprocessed.files.into_iter().skip(1).collect()));
 }
@@ -0,0 +1,57 @@
+//
+// Shader Compiler
+//
+
+// In-process GLSL to SPIR-V compilation with OpenGL semantics (the same as
+// glslc --target-env=opengl), available with the "shaderc" feature.
+
+use super::{shader::ShaderType, error::GamekitError};
+
+defaults!();
+
+pub fn is_available() -> bool {
+    return cfg!(feature = "shaderc");
+}
+
+#[cfg(feature = "shaderc")]
+pub fn compile_to_spirv(shader_type: ShaderType, source: &str, name: &str, entry_point: &str) -> Result<Vec<u8>, GamekitError> {
+
+    let compile_error = |log: String| GamekitError::ShaderCompile { file: name.to_string(), log };
+
+    let mut compiler = match shaderc::Compiler::new() {
+        Some(compiler) => compiler,
+        None => { return Err(compile_error("failed to initialize shaderc".to_string())); }
+    };
+
+    let mut options = match shaderc::CompileOptions::new() {
+        Some(options) => options,
+        None => { return Err(compile_error("failed to initialize shaderc".to_string())); }
+    };
+
+    options.set_target_env(shaderc::TargetEnv::OpenGL, shaderc::EnvVersion::OpenGL4_5 as u32);
+
+    let kind = match shader_type {
+        ShaderType::VertexShader => shaderc::ShaderKind::Vertex,
+        ShaderType::TessControlShader => shaderc::ShaderKind::TessControl,
+        ShaderType::TessEvaluationShader => shaderc::ShaderKind::TessEvaluation,
+        ShaderType::GeometryShader => shaderc::ShaderKind::Geometry,
+        ShaderType::FragmentShader => shaderc::ShaderKind::Fragment,
+        ShaderType::ComputeShader => shaderc::ShaderKind::Compute
+    };
+
+    let artifact = match compiler.compile_into_spirv(source, kind, name, entry_point, Some(&options)) {
+        Ok(artifact) => artifact,
+        Err(err) => { return Err(compile_error(err.to_string())); }
+    };
+
+    if artifact.get_num_warnings() > 0 {
+        warn!("{}", artifact.get_warning_messages());
+    }
+
+    return Ok(artifact.as_binary_u8().to_vec());
+}
+
+#[cfg(not(feature = "shaderc"))]
+pub fn compile_to_spirv(_shader_type: ShaderType, _source: &str, name: &str, _entry_point: &str) -> Result<Vec<u8>, GamekitError> {
+    return Err(GamekitError::ShaderCompile { file: name.to_string(), log: "built without the shaderc feature".to_string() });
+}
@@ -0,0 +1,64 @@
+//
+// Shader Watcher
+//
+
+// Hot reloading of programs loaded from shader files: the application polls
+// the watcher between frames, programs whose files changed are recompiled and
+// relinked. Enabled by default in debug builds.
+
+use std::{cell::{Cell, RefCell}, rc::{Rc, Weak}, time::{Duration, Instant}};
+
+use super::program::ProgramState;
+
+defaults!();
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+thread_local! {
+    static ENABLED: Cell<bool> = Cell::new(cfg!(debug_assertions));
+    static LAST_POLL: Cell<Option<Instant>> = Cell::new(None);
+    static PROGRAMS: RefCell<Vec<Weak<ProgramState>>> = RefCell::new(Vec::new());
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.with(|value| value.set(enabled));
+}
+
+pub fn is_enabled() -> bool {
+    return ENABLED.with(|value| value.get());
+}
+
+pub(crate) fn watch(program: &Rc<ProgramState>) {
+    PROGRAMS.with(|programs| programs.borrow_mut().push(Rc::downgrade(program)));
+}
+
+// checks the watched files at most every 250ms
+pub fn poll() {
+
+    if !is_enabled() {
+        return;
+    }
+
+    let now = Instant::now();
+    let due = LAST_POLL.with(|last_poll| {
+        match last_poll.get() {
+            Some(time) if now.duration_since(time) < POLL_INTERVAL => false,
+            _ => { last_poll.set(Some(now)); true }
+        }
+    });
+
+    if !due {
+        return;
+    }
+
+    // reloading creates shaders and programs, so don't hold the registry borrowed meanwhile
+    let programs: Vec<Rc<ProgramState>> = PROGRAMS.with(|programs| {
+        let mut programs = programs.borrow_mut();
+        programs.retain(|program| program.strong_count() > 0);
+        programs.iter().filter_map(|program| program.upgrade()).collect()
+    });
+
+    for program in programs {
+        program.reload_if_modified();
+    }
+}
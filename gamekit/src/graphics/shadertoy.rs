@@ -0,0 +1,584 @@
+//
+// Shadertoy Runner
+//
+
+use std::{fs, time::{SystemTime, UNIX_EPOCH}};
+
+use super::{
+    api::{Api, BlendMode},
+    application::Executor,
+    backend::TextureFormat,
+    error::GamekitError,
+    primitives::{FullscreenTriangle, Primitives},
+    program::Program,
+    render_target::RenderTarget,
+    shader::{Shader, ShaderType},
+    texture::Texture,
+    types::{Vector2, Vector3, Vector4},
+    uniform::Uniform
+};
+
+defaults!();
+
+const FULLSCREEN_VERTEX_SHADER: &str = "shaders/fullscreen.vert";
+const DEFAULT_IMAGE_SHADER: &str = "shaders/shadertoy.glsl";
+const NUM_CHANNELS: usize = 4;
+const NUM_BUFFERS: usize = 4;
+const SAMPLE_RATE: f32 = 44100.0;
+
+// the Shadertoy uniform set, user code is inserted after the common code
+const SHADER_HEADER: &str = "#version 450 core
+
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform float iFrameRate;
+uniform int iFrame;
+uniform float iChannelTime[4];
+uniform vec3 iChannelResolution[4];
+uniform vec4 iMouse;
+uniform vec4 iDate;
+uniform float iSampleRate;
+
+layout (binding = 0) uniform sampler2D iChannel0;
+layout (binding = 1) uniform sampler2D iChannel1;
+layout (binding = 2) uniform sampler2D iChannel2;
+layout (binding = 3) uniform sampler2D iChannel3;
+
+layout (location = 0) out vec4 shadertoy_FragColor;
+";
+
+const SHADER_FOOTER: &str = "
+void main() {
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, gl_FragCoord.xy);
+#ifdef SHADERTOY_IMAGE
+    color.a = 1.0;
+#endif
+    shadertoy_FragColor = color;
+}
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShadertoyBuffer {
+    A,
+    B,
+    C,
+    D
+}
+
+impl ShadertoyBuffer {
+    fn index(&self) -> usize {
+        match self {
+            ShadertoyBuffer::A => 0,
+            ShadertoyBuffer::B => 1,
+            ShadertoyBuffer::C => 2,
+            ShadertoyBuffer::D => 3
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelInput {
+    None,
+    // PNG file
+    Texture(String),
+    // output of a buffer pass, the previous frame when read by the same or an earlier pass
+    Buffer(ShadertoyBuffer)
+}
+
+#[derive(Clone, Debug)]
+pub struct ShadertoyPassConfig {
+    // file containing mainImage(out vec4 fragColor, in vec2 fragCoord), pasted unchanged
+    pub source_file: String,
+    pub channels: [ChannelInput; NUM_CHANNELS]
+}
+
+impl ShadertoyPassConfig {
+    pub fn new(source_file: &str) -> ShadertoyPassConfig {
+        return ShadertoyPassConfig {
+            source_file: source_file.to_string(),
+            channels: [ChannelInput::None, ChannelInput::None, ChannelInput::None, ChannelInput::None]
+        };
+    }
+
+    // channel index 0 to 3
+    pub fn with_channel(mut self, index: usize, input: ChannelInput) -> Result<ShadertoyPassConfig, GamekitError> {
+        let channel = match self.channels.get_mut(index) {
+            Some(channel) => channel,
+            None => { return Err(GamekitError::invalid_argument(format!("channel index {} out of range 0..{}", index, NUM_CHANNELS))); }
+        };
+        *channel = input;
+        return Ok(self);
+    }
+}
+
+// Shadertoy tabs: Common, Buffer A-D and Image
+#[derive(Clone, Debug)]
+pub struct ShadertoyConfig {
+    pub common_file: Option<String>,
+    pub buffers: [Option<ShadertoyPassConfig>; NUM_BUFFERS],
+    pub image: ShadertoyPassConfig
+}
+
+impl ShadertoyConfig {
+    pub fn new(image_file: &str) -> ShadertoyConfig {
+        return ShadertoyConfig {
+            common_file: None,
+            buffers: [None, None, None, None],
+            image: ShadertoyPassConfig::new(image_file)
+        };
+    }
+
+    // --shadertoy [image] [--common <file>] [--buffer-a..d <file>] [--channel0..3 <png|A|B|C|D>],
+    // channel options apply to the pass named right before them. None without --shadertoy.
+    pub fn from_args(args: &[String]) -> Result<Option<ShadertoyConfig>, GamekitError> {
+
+        let start = match args.iter().position(|arg| arg == "--shadertoy") {
+            Some(start) => start,
+            None => { return Ok(None); }
+        };
+
+        let mut args = args[start + 1..].iter().peekable();
+
+        let image_file = match args.peek() {
+            Some(arg) if !arg.starts_with("--") => args.next().unwrap().as_str(),
+            _ => DEFAULT_IMAGE_SHADER
+        };
+
+        let mut config = ShadertoyConfig::new(image_file);
+
+        // None is the image pass
+        let mut current_buffer: Option<usize> = None;
+
+        while let Some(option) = args.next() {
+
+            let value = match args.next() {
+                Some(value) => value,
+                None => { return Err(GamekitError::invalid_argument(format!("missing value for {}", option))); }
+            };
+
+            match option.as_str() {
+                "--common" => { config.common_file = Some(value.clone()); },
+                "--buffer-a" | "--buffer-b" | "--buffer-c" | "--buffer-d" => {
+                    let index = parse_buffer(&option[option.len() - 1..]).unwrap().index();
+                    config.buffers[index] = Some(ShadertoyPassConfig::new(value));
+                    current_buffer = Some(index);
+                },
+                "--channel0" | "--channel1" | "--channel2" | "--channel3" => {
+                    let channel = option[option.len() - 1..].parse::<usize>().unwrap();
+                    let input = match parse_buffer(value) {
+                        Some(buffer) => ChannelInput::Buffer(buffer),
+                        None => ChannelInput::Texture(value.clone())
+                    };
+                    let pass = match current_buffer {
+                        Some(index) => config.buffers[index].as_mut().unwrap(),
+                        None => &mut config.image
+                    };
+                    pass.channels[channel] = input;
+                },
+                _ => { return Err(GamekitError::invalid_argument(format!("unknown shadertoy option {}", option))); }
+            }
+        }
+
+        return Ok(Some(config));
+    }
+}
+
+fn parse_buffer(name: &str) -> Option<ShadertoyBuffer> {
+    return match name.to_ascii_uppercase().as_str() {
+        "A" => Some(ShadertoyBuffer::A),
+        "B" => Some(ShadertoyBuffer::B),
+        "C" => Some(ShadertoyBuffer::C),
+        "D" => Some(ShadertoyBuffer::D),
+        _ => None
+    };
+}
+
+struct ShaderUniforms {
+    resolution: Uniform<Vector3>,
+    time: Uniform<f32>,
+    time_delta: Uniform<f32>,
+    frame_rate: Uniform<f32>,
+    frame: Uniform<i32>,
+    channel_time: Uniform<[f32]>,
+    channel_resolution: Uniform<[Vector3]>,
+    mouse: Uniform<Vector4>,
+    date: Uniform<Vector4>,
+    sample_rate: Uniform<f32>
+}
+
+impl ShaderUniforms {
+    fn new() -> ShaderUniforms {
+        return ShaderUniforms {
+            resolution: Uniform::new("iResolution"),
+            time: Uniform::new("iTime"),
+            time_delta: Uniform::new("iTimeDelta"),
+            frame_rate: Uniform::new("iFrameRate"),
+            frame: Uniform::new("iFrame"),
+            channel_time: Uniform::new("iChannelTime"),
+            channel_resolution: Uniform::new("iChannelResolution"),
+            mouse: Uniform::new("iMouse"),
+            date: Uniform::new("iDate"),
+            sample_rate: Uniform::new("iSampleRate")
+        };
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ChannelBinding {
+    texture_id: u32,
+    width: u32,
+    height: u32
+}
+
+impl ChannelBinding {
+    fn of(texture: &Texture) -> ChannelBinding {
+        return ChannelBinding { texture_id: texture.id(), width: texture.width(), height: texture.height() };
+    }
+}
+
+enum Channel {
+    None,
+    Texture(Texture),
+    Buffer(usize)
+}
+
+struct Pass {
+    program: Program,
+    uniforms: ShaderUniforms,
+    channels: Vec<Channel>
+}
+
+// buffer passes render into one of two targets, so they can read their own previous frame
+struct BufferTargets {
+    targets: [RenderTarget; 2],
+    latest: usize,
+    cleared: bool
+}
+
+impl BufferTargets {
+    fn new(width: u32, height: u32) -> Result<BufferTargets, GamekitError> {
+        return Ok(BufferTargets {
+            targets: [
+                RenderTarget::new(width, height, &[TextureFormat::Rgba16F], None)?,
+                RenderTarget::new(width, height, &[TextureFormat::Rgba16F], None)?
+            ],
+            latest: 0,
+            cleared: false
+        });
+    }
+
+    fn latest(&self) -> &Texture {
+        return self.targets[self.latest].texture();
+    }
+}
+
+// Renders Shadertoy style fullscreen shaders: buffer passes A-D in order,
+// then the image pass to the default framebuffer.
+pub struct ShadertoyRunner {
+    buffers: Vec<Option<(Pass, BufferTargets)>>,
+    image: Pass,
+    triangle: FullscreenTriangle,
+    // iMouse.xy, the last position with the button down
+    mouse_position: Vector2,
+    width: u32,
+    height: u32
+}
+
+impl ShadertoyRunner {
+
+    pub fn new(config: &ShadertoyConfig, width: u32, height: u32) -> Result<ShadertoyRunner, GamekitError> {
+
+        let common = match &config.common_file {
+            Some(file) => read_source(file)?,
+            None => String::new()
+        };
+
+        let mut buffers = Vec::with_capacity(NUM_BUFFERS);
+        for buffer in &config.buffers {
+            buffers.push(match buffer {
+                Some(pass_config) => Some((create_pass(pass_config, &common, false)?, BufferTargets::new(width, height)?)),
+                None => None
+            });
+        }
+
+        let image = create_pass(&config.image, &common, true)?;
+
+        let runner = ShadertoyRunner {
+            buffers,
+            image,
+            triangle: FullscreenTriangle::new(),
+            mouse_position: Vector2::zero(),
+            width,
+            height
+        };
+
+        return Ok(runner);
+    }
+
+    // buffers are cleared when the size changes, as on Shadertoy
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), GamekitError> {
+
+        if width == self.width && height == self.height {
+            return Ok(());
+        }
+
+        for buffer in self.buffers.iter_mut().flatten() {
+            buffer.1 = BufferTargets::new(width, height)?;
+        }
+
+        self.width = width;
+        self.height = height;
+
+        return Ok(());
+    }
+
+    pub fn render(&mut self, api: &dyn Api) {
+
+        let mouse = self.mouse(api);
+
+        api.set_blend_mode(BlendMode::Opaque);
+
+        for index in 0..self.buffers.len() {
+
+            let channels = match &self.buffers[index] {
+                Some((pass, _)) => self.channel_bindings(pass),
+                None => { continue; }
+            };
+
+            if let Some((pass, targets)) = &mut self.buffers[index] {
+
+                // new targets start out black and transparent
+                if !targets.cleared {
+                    api.clear_color(0.0, 0.0, 0.0, 0.0);
+                    for target in &targets.targets {
+                        api.set_render_target(Some(target));
+                        api.clear();
+                    }
+                    targets.cleared = true;
+                }
+
+                let target = &targets.targets[1 - targets.latest];
+                api.set_render_target(Some(target));
+                draw_pass(api, &self.triangle, pass, &channels, mouse, target.width(), target.height());
+                targets.latest = 1 - targets.latest;
+            }
+        }
+
+        api.set_render_target(None);
+
+        let channels = self.channel_bindings(&self.image);
+        draw_pass(api, &self.triangle, &mut self.image, &channels, mouse, self.width, self.height);
+
+        for unit in 0..NUM_CHANNELS {
+            Primitives::unbind_texture(unit as u32);
+        }
+
+        api.set_blend_mode(BlendMode::Normal);
+    }
+
+    // xy: last position while the button is down, zw: click position, z negative
+    // when released, w negative after the click frame. Shadertoy has y pointing up.
+    fn mouse(&mut self, api: &dyn Api) -> Vector4 {
+
+        let mouse = api.get_mouse();
+        let surface_height = api.get_metrics().height as f32;
+
+        if mouse.left_button {
+            self.mouse_position = Vector2::new(mouse.x, surface_height - mouse.y);
+        }
+
+        let click_x = if mouse.left_button { mouse.click_x } else { -mouse.click_x };
+        let click_y = if mouse.clicked { surface_height - mouse.click_y } else { -(surface_height - mouse.click_y) };
+
+        return Vector4::new(self.mouse_position.x, self.mouse_position.y, click_x, click_y);
+    }
+
+    fn channel_bindings(&self, pass: &Pass) -> Vec<Option<ChannelBinding>> {
+        return pass.channels.iter().map(|channel| {
+            match channel {
+                Channel::None => None,
+                Channel::Texture(texture) => Some(ChannelBinding::of(texture)),
+                Channel::Buffer(index) => self.buffers[*index].as_ref().map(|(_, targets)| ChannelBinding::of(targets.latest()))
+            }
+        }).collect();
+    }
+
+    pub fn free(&mut self) {
+        info!("ShadertoyRunner free");
+        self.triangle.free();
+    }
+
+}
+
+impl Drop for ShadertoyRunner {
+    fn drop(&mut self) {
+        debug!("drop shadertoy runner");
+        self.free();
+    }
+}
+
+// Ready-made executor running a ShadertoyRunner. Executor::new runs the
+// default image shader, other configurations are passed through the
+// application's executor factory:
+//
+//     let config = ShadertoyConfig::new("image.glsl");
+//     let app = Application::with_factory(60, GraphicsMode::Window, |api| ShadertoyExecutor::with_config(api, &config))?;
+pub struct ShadertoyExecutor {
+    runner: ShadertoyRunner
+}
+
+impl ShadertoyExecutor {
+    pub fn with_config(api: &mut dyn Api, config: &ShadertoyConfig) -> Result<ShadertoyExecutor, GamekitError> {
+        info!("ShadertoyExecutor new");
+
+        let metrics = api.get_metrics();
+        let runner = ShadertoyRunner::new(config, metrics.width, metrics.height)?;
+
+        return Ok(ShadertoyExecutor { runner });
+    }
+}
+
+impl Executor for ShadertoyExecutor {
+
+    fn new(api: &mut dyn Api) -> Result<ShadertoyExecutor, GamekitError> {
+        return Self::with_config(api, &ShadertoyConfig::new(DEFAULT_IMAGE_SHADER));
+    }
+
+    fn initialize(&mut self, _api: &mut dyn Api) {
+    }
+
+    fn free(&mut self, _api: &mut dyn Api) {
+        info!("ShadertoyExecutor free");
+    }
+
+    fn update_layout(&mut self, api: &mut dyn Api) {
+        let metrics = api.get_metrics();
+        if let Err(err) = self.runner.resize(metrics.width, metrics.height) {
+            error!("failed to resize shadertoy buffers: {}", err);
+        }
+    }
+
+    fn update_state(&mut self, _api: &mut dyn Api, _delta: f32) {
+    }
+
+    fn update_graphics(&mut self, api: &mut dyn Api) {
+        self.runner.render(api);
+    }
+
+}
+
+fn read_source(file: &str) -> Result<String, GamekitError> {
+    return match fs::read_to_string(file) {
+        Ok(source) => Ok(source),
+        Err(err) => Err(GamekitError::io(file, err))
+    };
+}
+
+fn create_pass(config: &ShadertoyPassConfig, common: &str, is_image: bool) -> Result<Pass, GamekitError> {
+
+    let user_source = read_source(&config.source_file)?;
+
+    // #line keeps compiler messages pointing at lines of the pasted code
+    let source = format!("{}{}\n#line 1\n{}\n#line 1\n{}\n{}",
+        SHADER_HEADER,
+        if is_image { "#define SHADERTOY_IMAGE" } else { "" },
+        common,
+        user_source,
+        SHADER_FOOTER);
+
+    let vertex_shader = Shader::new(ShaderType::VertexShader, FULLSCREEN_VERTEX_SHADER)?;
+    let fragment_shader = Shader::from_source(ShaderType::FragmentShader, &source, &config.source_file)?;
+    let program = Program::new(&vec!(vertex_shader, fragment_shader))?;
+
+    let mut channels = Vec::with_capacity(NUM_CHANNELS);
+    for input in &config.channels {
+        channels.push(match input {
+            ChannelInput::None => Channel::None,
+            ChannelInput::Texture(file) => Channel::Texture(Texture::new(file)?),
+            ChannelInput::Buffer(buffer) => Channel::Buffer(buffer.index())
+        });
+    }
+
+    let pass = Pass {
+        program,
+        uniforms: ShaderUniforms::new(),
+        channels
+    };
+
+    return Ok(pass);
+}
+
+fn draw_pass(api: &dyn Api, triangle: &FullscreenTriangle, pass: &mut Pass, channels: &[Option<ChannelBinding>], mouse: Vector4, width: u32, height: u32) {
+
+    pass.program.use_program();
+
+    let metrics = api.get_metrics();
+
+    let uniforms = &mut pass.uniforms;
+
+    uniforms.resolution.set(Vector3::new(width as f32, height as f32, 1.0));
+    uniforms.time.set(metrics.time_seconds);
+    uniforms.time_delta.set(metrics.delta_seconds);
+    uniforms.frame_rate.set(if metrics.delta_seconds > 0.0 { 1.0 / metrics.delta_seconds } else { 0.0 });
+    uniforms.frame.set(metrics.frame_counter as i32);
+    uniforms.sample_rate.set(SAMPLE_RATE);
+    uniforms.date.set(current_date());
+
+    uniforms.mouse.set(mouse);
+
+    let mut channel_resolution = [Vector3::zero(); NUM_CHANNELS];
+
+    for (index, channel) in channels.iter().enumerate() {
+        match channel {
+            Some(binding) => {
+                Primitives::bind_texture(binding.texture_id, index as u32);
+                channel_resolution[index] = Vector3::new(binding.width as f32, binding.height as f32, 1.0);
+            },
+            None => {
+                Primitives::unbind_texture(index as u32);
+            }
+        }
+    }
+
+    uniforms.channel_resolution.set(&channel_resolution);
+    uniforms.channel_time.set(&[metrics.time_seconds; NUM_CHANNELS]);
+
+    triangle.draw(api);
+}
+
+// year, month (0-11), day (1-31), seconds of the day, in UTC
+fn current_date() -> Vector4 {
+
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    let days = (since_epoch.as_secs() / 86400) as i64;
+    let seconds = (since_epoch.as_secs_f64() - (days * 86400) as f64) as f32;
+
+    // civil date from days since 1970-01-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return Vector4::new(year as f32, (month - 1) as f32, day as f32, seconds);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShadertoyPassConfig, ChannelInput, ShadertoyBuffer};
+
+    #[test]
+    fn channels_out_of_range_are_rejected() {
+        let config = ShadertoyPassConfig::new("image.glsl").with_channel(3, ChannelInput::Buffer(ShadertoyBuffer::A)).unwrap();
+        assert!(matches!(config.channels[3], ChannelInput::Buffer(ShadertoyBuffer::A)));
+        assert!(ShadertoyPassConfig::new("image.glsl").with_channel(4, ChannelInput::None).is_err());
+    }
+
+}
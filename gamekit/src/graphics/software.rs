@@ -0,0 +1,1177 @@
+//
+// Software Backend
+//
+
+// CPU rasterizer implementing the fixed sprite pipeline: vertex positions are
+// given in pixels (origin top-left, as produced by SpriteBatch with the
+// default x/y ranges), attribute 0 is the position, 1 the color and 2 the
+// texture coordinates. The fragment color is vertex color * texel of unit 0.
+// Shaders and uniforms are accepted but not interpreted. Render targets only
+// receive color attachment 0, their rows are stored bottom-up as in GL.
+// Depth and stencil attachments are accepted but not tested. Draws without
+// a position attribute are fullscreen passes (vertices from gl_VertexID) and
+// copy the texture of unit 0 onto the whole target.
+
+use std::{cell::{RefCell, Ref}, collections::HashMap};
+
+use super::{
+    api::{BlendMode, MemoryBarrier},
+    primitives::DrawMode,
+    buffers::{BufferType, BufferUsage},
+    error::GamekitError,
+    reflection::ProgramInterface,
+    shader::ShaderType,
+    backend::{Backend, HostMapping, TextureFormat, DepthStencilFormat, ShaderCode, VertexAttribute, AttributeType, UniformValue}
+};
+
+defaults!();
+
+const POSITION_LOCATION: u32 = 0;
+const COLOR_LOCATION: u32 = 1;
+const TEXCOORD_LOCATION: u32 = 2;
+
+#[derive(Clone, Copy)]
+struct Fragment {
+    x: f32,
+    y: f32,
+    color: [f32; 4],
+    texcoords: [f32; 2]
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>
+}
+
+struct TextureImage {
+    levels: Vec<MipLevel>,
+    repeat: bool
+}
+
+// buffer binding slot of a vertex array
+#[derive(Clone, Copy, Default)]
+struct BufferBinding {
+    buffer: u32,
+    offset: usize,
+    stride: usize,
+    divisor: u32
+}
+
+// attribute resolved against its buffer binding
+#[derive(Clone, Copy)]
+struct AttributeBinding {
+    attribute: VertexAttribute,
+    binding: BufferBinding
+}
+
+#[derive(Default)]
+struct VertexArrayState {
+    // attributes with the binding slot they read from
+    attributes: Vec<(VertexAttribute, u32)>,
+    bindings: HashMap<u32, BufferBinding>,
+    index_buffer: u32
+}
+
+struct State {
+    width: usize,
+    height: usize,
+    color_buffer: Vec<u8>,
+    clear_color: [f32; 4],
+    blend_mode: BlendMode,
+    cull_back_faces: bool,
+    next_id: u32,
+    buffers: HashMap<u32, Vec<u8>>,
+    // memory written by the application, copied to buffers before drawing
+    mapped_buffers: HashMap<u32, HostMapping>,
+    bound_buffers: HashMap<BufferType, u32>,
+    vertex_arrays: HashMap<u32, VertexArrayState>,
+    current_vertex_array: u32,
+    textures: HashMap<u32, TextureImage>,
+    texture_units: HashMap<u32, u32>,
+    framebuffers: HashMap<u32, Vec<u32>>,
+    current_framebuffer: u32,
+    current_program: u32
+}
+
+pub struct SoftwareBackend {
+    state: RefCell<State>
+}
+
+impl SoftwareBackend {
+
+    pub fn new(width: u32, height: u32) -> SoftwareBackend {
+
+        let mut vertex_arrays = HashMap::new();
+        vertex_arrays.insert(0, VertexArrayState::default());
+
+        let state = State {
+            width: width as usize,
+            height: height as usize,
+            color_buffer: vec![0u8; width as usize * height as usize * 4],
+            clear_color: [0.0, 0.0, 0.0, 0.0],
+            blend_mode: BlendMode::Normal,
+            cull_back_faces: false,
+            next_id: 1,
+            buffers: HashMap::new(),
+            mapped_buffers: HashMap::new(),
+            bound_buffers: HashMap::new(),
+            vertex_arrays,
+            current_vertex_array: 0,
+            textures: HashMap::new(),
+            texture_units: HashMap::new(),
+            framebuffers: HashMap::new(),
+            current_framebuffer: 0,
+            current_program: 0
+        };
+
+        return SoftwareBackend {
+            state: RefCell::new(state)
+        };
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        let state = self.state.borrow();
+        return (state.width as u32, state.height as u32);
+    }
+
+    // RGBA8 pixels, rows from top to bottom
+    pub fn framebuffer(&self) -> Ref<'_, Vec<u8>> {
+        return Ref::map(self.state.borrow(), |state| &state.color_buffer);
+    }
+
+    fn allocate_id(&self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id;
+        state.next_id += 1;
+        return id;
+    }
+
+    fn sync_mapped_buffers(&self) {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        for (id, mapping) in &state.mapped_buffers {
+            if let Some(buffer) = state.buffers.get_mut(id) {
+                buffer.copy_from_slice(mapping.bytes());
+            }
+        }
+    }
+
+    fn draw(&self, mode: DrawMode, indices: &[u32], instance: usize) {
+
+        let mut state = self.state.borrow_mut();
+
+        let vertex_array = match state.vertex_arrays.get(&state.current_vertex_array) {
+            Some(vertex_array) => vertex_array,
+            None => { return; }
+        };
+
+        let position = find_attribute(vertex_array, POSITION_LOCATION);
+        let color = find_attribute(vertex_array, COLOR_LOCATION);
+        let texcoords = find_attribute(vertex_array, TEXCOORD_LOCATION);
+
+        let position = match position {
+            Some(position) => position,
+            None => {
+                draw_fullscreen(&mut state);
+                return;
+            }
+        };
+
+        let mut fragments = Vec::with_capacity(indices.len());
+
+        for index in indices {
+            let index = *index as usize;
+            let xyz = read_attribute(&state.buffers, &position, index, instance, [0.0, 0.0, 0.0, 1.0]);
+            let rgba = match &color {
+                Some(color) => read_attribute(&state.buffers, color, index, instance, [0.0, 0.0, 0.0, 1.0]),
+                None => [1.0, 1.0, 1.0, 1.0]
+            };
+            let uv = match &texcoords {
+                Some(texcoords) => read_attribute(&state.buffers, texcoords, index, instance, [0.0, 0.0, 0.0, 1.0]),
+                None => [0.0, 0.0, 0.0, 1.0]
+            };
+
+            fragments.push(Fragment {
+                x: xyz[0],
+                y: xyz[1],
+                color: rgba,
+                texcoords: [uv[0], uv[1]]
+            });
+        }
+
+        let texture_id = state.texture_units.get(&0).copied().unwrap_or(0);
+
+        let triangle_count = match mode {
+            DrawMode::Triangles => fragments.len() / 3,
+            DrawMode::TriangleStrip => if fragments.len() >= 3 { fragments.len() - 2 } else { 0 }
+        };
+
+        for i in 0..triangle_count {
+
+            let (a, b, c) = match mode {
+                DrawMode::Triangles => (fragments[i*3], fragments[i*3+1], fragments[i*3+2]),
+                DrawMode::TriangleStrip => {
+                    // keep a consistent winding for every other triangle
+                    if i % 2 == 0 {
+                        (fragments[i], fragments[i+1], fragments[i+2])
+                    } else {
+                        (fragments[i+1], fragments[i], fragments[i+2])
+                    }
+                }
+            };
+
+            rasterize_triangle(&mut state, texture_id, &a, &b, &c);
+        }
+    }
+
+}
+
+impl Backend for SoftwareBackend {
+
+    fn initialize(&self) {
+        let mut state = self.state.borrow_mut();
+        state.blend_mode = BlendMode::Normal;
+        state.cull_back_faces = true;
+    }
+
+    fn viewport(&self, _x: u32, _y: u32, width: u32, height: u32) {
+        let mut state = self.state.borrow_mut();
+        if state.current_framebuffer != 0 {
+            // render targets are always drawn in full
+            return;
+        }
+        if state.width != width as usize || state.height != height as usize {
+            state.width = width as usize;
+            state.height = height as usize;
+            state.color_buffer = vec![0u8; state.width * state.height * 4];
+        }
+    }
+
+    fn clear_color(&self, red: f32, green: f32, blue: f32, alpha: f32) {
+        self.state.borrow_mut().clear_color = [red, green, blue, alpha];
+    }
+
+    fn clear(&self) {
+        let mut state = self.state.borrow_mut();
+        let clear_color = state.clear_color.map(to_byte);
+
+        if let Some(attachments) = state.framebuffers.get(&state.current_framebuffer).cloned() {
+            let texel = clear_color.map(from_byte);
+            for id in attachments {
+                if let Some(texture) = state.textures.get_mut(&id) {
+                    texture.levels[0].pixels.fill(texel);
+                }
+            }
+            return;
+        }
+
+        for pixel in state.color_buffer.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&clear_color);
+        }
+    }
+
+    fn set_blend_mode(&self, blend_mode: BlendMode) {
+        self.state.borrow_mut().blend_mode = blend_mode;
+    }
+
+    // the framebuffer size is known from the bound target
+    fn read_pixels(&self, x: u32, y: u32, width: u32, height: u32, _framebuffer_height: u32) -> Vec<u8> {
+
+        let state = self.state.borrow();
+
+        if let Some(attachments) = state.framebuffers.get(&state.current_framebuffer) {
+            return read_texture(&state, attachments[0], x, y, width, height);
+        }
+
+        return read_color_buffer(&state, x, y, width, height);
+    }
+
+    fn draw_arrays(&self, mode: DrawMode, ofs: usize, count: usize) {
+        let indices: Vec<u32> = (ofs..ofs+count).map(|index| index as u32).collect();
+        self.sync_mapped_buffers();
+        self.draw(mode, &indices, 0);
+    }
+
+    fn draw_elements(&self, mode: DrawMode, count: usize) {
+        self.draw_elements_instanced(mode, count, 1);
+    }
+
+    fn draw_elements_instanced(&self, mode: DrawMode, count: usize, num_instances: usize) {
+
+        self.sync_mapped_buffers();
+
+        let indices: Vec<u32> = {
+            let state = self.state.borrow();
+            let index_buffer = state.vertex_arrays.get(&state.current_vertex_array).map(|vertex_array| vertex_array.index_buffer).unwrap_or(0);
+            let data = match state.buffers.get(&index_buffer) {
+                Some(data) => data,
+                None => { return; }
+            };
+            data.chunks_exact(4).take(count).map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect()
+        };
+
+        // attributes with a divisor advance per instance
+        for instance in 0..num_instances {
+            self.draw(mode, &indices, instance);
+        }
+    }
+
+    // shaders are not executed, compute work is skipped
+    fn dispatch_compute(&self, _x: u32, _y: u32, _z: u32) {
+    }
+
+    fn dispatch_compute_indirect(&self, _offset: usize) {
+    }
+
+    fn memory_barrier(&self, _barriers: &[MemoryBarrier]) {
+    }
+
+    fn create_buffer(&self) -> u32 {
+        let id = self.allocate_id();
+        self.state.borrow_mut().buffers.insert(id, Vec::new());
+        return id;
+    }
+
+    fn delete_buffer(&self, id: u32) {
+        let mut state = self.state.borrow_mut();
+        state.buffers.remove(&id);
+        state.mapped_buffers.remove(&id);
+    }
+
+    fn bind_buffer(&self, id: u32, buffer_type: &BufferType) {
+        let mut state = self.state.borrow_mut();
+        if *buffer_type == BufferType::IndexBuffer {
+            // the index buffer binding is part of the vertex array state
+            let current_vertex_array = state.current_vertex_array;
+            if let Some(vertex_array) = state.vertex_arrays.get_mut(&current_vertex_array) {
+                vertex_array.index_buffer = id;
+            }
+        }
+        state.bound_buffers.insert(*buffer_type, id);
+    }
+
+    fn bind_buffer_base(&self, id: u32, _binding_point: u32, buffer_type: &BufferType) {
+        self.state.borrow_mut().bound_buffers.insert(*buffer_type, id);
+    }
+
+    fn unbind_buffer(&self, _id: u32, buffer_type: &BufferType) {
+        self.bind_buffer(0, buffer_type);
+    }
+
+    // mapped buffers have immutable storage
+    fn buffer_data(&self, id: u32, _buffer_usage: &BufferUsage, data: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        if state.mapped_buffers.contains_key(&id) {
+            return;
+        }
+        if let Some(buffer) = state.buffers.get_mut(&id) {
+            buffer.clear();
+            buffer.extend_from_slice(data);
+        }
+    }
+
+    fn buffer_sub_data(&self, id: u32, offset: usize, data: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        if state.mapped_buffers.contains_key(&id) {
+            return;
+        }
+        if let Some(buffer) = state.buffers.get_mut(&id) {
+            if buffer.len() < offset + data.len() {
+                buffer.resize(offset + data.len(), 0);
+            }
+            buffer[offset..offset+data.len()].copy_from_slice(data);
+        }
+    }
+
+    fn create_mapped_buffer(&self, size: usize) -> Result<(u32, *mut u8), GamekitError> {
+        let id = self.allocate_id();
+        let mapping = HostMapping::new(size);
+        let data = mapping.as_ptr();
+        let mut state = self.state.borrow_mut();
+        state.buffers.insert(id, vec![0u8; mapping.bytes().len()]);
+        state.mapped_buffers.insert(id, mapping);
+        return Ok((id, data));
+    }
+
+    // draws complete before returning, there is nothing to wait for
+    fn fence_sync(&self) -> u64 {
+        return 0;
+    }
+
+    fn wait_fence(&self, _fence: u64) {
+    }
+
+    fn delete_fence(&self, _fence: u64) {
+    }
+
+    fn create_vertex_array(&self) -> u32 {
+        let id = self.allocate_id();
+        self.state.borrow_mut().vertex_arrays.insert(id, VertexArrayState::default());
+        return id;
+    }
+
+    fn delete_vertex_array(&self, id: u32) {
+        if id != 0 {
+            self.state.borrow_mut().vertex_arrays.remove(&id);
+        }
+    }
+
+    fn bind_vertex_array(&self, id: u32) {
+        let mut state = self.state.borrow_mut();
+        state.current_vertex_array = id;
+        let index_buffer = state.vertex_arrays.get(&id).map(|vertex_array| vertex_array.index_buffer).unwrap_or(0);
+        state.bound_buffers.insert(BufferType::IndexBuffer, index_buffer);
+    }
+
+    fn unbind_vertex_array(&self) {
+        self.bind_vertex_array(0);
+    }
+
+    fn vertex_array_attribute(&self, vertex_array: u32, binding: u32, attribute: &VertexAttribute) {
+        let mut state = self.state.borrow_mut();
+        if let Some(vertex_array) = state.vertex_arrays.get_mut(&vertex_array) {
+            vertex_array.attributes.retain(|(existing, _)| existing.location != attribute.location);
+            vertex_array.attributes.push((*attribute, binding));
+        }
+    }
+
+    fn vertex_array_buffer(&self, vertex_array: u32, binding: u32, buffer: u32, offset: usize, stride: usize, divisor: u32) {
+        let mut state = self.state.borrow_mut();
+        if let Some(vertex_array) = state.vertex_arrays.get_mut(&vertex_array) {
+            vertex_array.bindings.insert(binding, BufferBinding { buffer, offset, stride, divisor });
+        }
+    }
+
+    fn vertex_array_index_buffer(&self, vertex_array: u32, buffer: u32) {
+        let mut state = self.state.borrow_mut();
+        if let Some(vertex_array_state) = state.vertex_arrays.get_mut(&vertex_array) {
+            vertex_array_state.index_buffer = buffer;
+        }
+        if state.current_vertex_array == vertex_array {
+            state.bound_buffers.insert(BufferType::IndexBuffer, buffer);
+        }
+    }
+
+    fn create_texture(&self, width: u32, height: u32, format: TextureFormat, pixels: &[u8]) -> Result<u32, GamekitError> {
+
+        if width == 0 || height == 0 {
+            return Err(GamekitError::invalid_argument(format!("invalid texture size {}x{}", width, height)));
+        }
+
+        if format == TextureFormat::Rgba16F {
+            return Err(GamekitError::invalid_argument("float textures are not supported by the software backend"));
+        }
+
+        let bytes_per_pixel = format.bytes_per_pixel();
+        let pixel_count = width as usize * height as usize;
+
+        if pixels.len() < pixel_count * bytes_per_pixel {
+            return Err(GamekitError::invalid_argument("texture data too small"));
+        }
+
+        let base_level = MipLevel {
+            width: width as usize,
+            height: height as usize,
+            pixels: pixels.chunks_exact(bytes_per_pixel).take(pixel_count).map(|texel| {
+                let alpha = if bytes_per_pixel == 4 { texel[3] } else { 255 };
+                [from_byte(texel[0]), from_byte(texel[1]), from_byte(texel[2]), from_byte(alpha)]
+            }).collect()
+        };
+
+        let texture = TextureImage {
+            levels: generate_mipmaps(base_level),
+            repeat: true
+        };
+
+        let id = self.allocate_id();
+        self.state.borrow_mut().textures.insert(id, texture);
+
+        return Ok(id);
+    }
+
+    fn delete_texture(&self, id: u32) {
+        self.state.borrow_mut().textures.remove(&id);
+    }
+
+    fn bind_texture(&self, id: u32, bind_location: u32) {
+        self.state.borrow_mut().texture_units.insert(bind_location, id);
+    }
+
+    fn unbind_texture(&self, bind_location: u32) {
+        self.state.borrow_mut().texture_units.insert(bind_location, 0);
+    }
+
+    fn create_render_texture(&self, width: u32, height: u32, _format: TextureFormat) -> Result<u32, GamekitError> {
+
+        if width == 0 || height == 0 {
+            return Err(GamekitError::invalid_argument(format!("invalid texture size {}x{}", width, height)));
+        }
+
+        let base_level = MipLevel {
+            width: width as usize,
+            height: height as usize,
+            pixels: vec![[0.0; 4]; width as usize * height as usize]
+        };
+
+        let texture = TextureImage {
+            levels: vec![base_level],
+            repeat: false
+        };
+
+        let id = self.allocate_id();
+        self.state.borrow_mut().textures.insert(id, texture);
+
+        return Ok(id);
+    }
+
+    fn create_renderbuffer(&self, _width: u32, _height: u32, _format: DepthStencilFormat) -> Result<u32, GamekitError> {
+        return Ok(self.allocate_id());
+    }
+
+    fn delete_renderbuffer(&self, _id: u32) {
+    }
+
+    fn create_framebuffer(&self, color_attachments: &[u32], _depth_stencil: Option<(u32, DepthStencilFormat)>) -> Result<u32, GamekitError> {
+
+        if color_attachments.is_empty() {
+            return Err(GamekitError::invalid_argument("framebuffer incomplete: no color attachment"));
+        }
+
+        let id = self.allocate_id();
+        self.state.borrow_mut().framebuffers.insert(id, color_attachments.to_vec());
+
+        return Ok(id);
+    }
+
+    fn delete_framebuffer(&self, id: u32) {
+        let mut state = self.state.borrow_mut();
+        state.framebuffers.remove(&id);
+        if state.current_framebuffer == id {
+            state.current_framebuffer = 0;
+        }
+    }
+
+    fn bind_framebuffer(&self, id: u32) {
+        self.state.borrow_mut().current_framebuffer = id;
+    }
+
+    fn read_framebuffer(&self, id: u32, attachment: u32, width: u32, height: u32) -> Vec<u8> {
+
+        let state = self.state.borrow();
+
+        let texture_id = match state.framebuffers.get(&id) {
+            Some(attachments) => attachments.get(attachment as usize).copied().unwrap_or(0),
+            None => { return read_color_buffer(&state, 0, 0, width, height); }
+        };
+
+        return read_texture(&state, texture_id, 0, 0, width, height);
+    }
+
+    // shaders are ignored, sources avoid requiring compiled binaries
+    fn supports_spirv(&self) -> bool {
+        return false;
+    }
+
+    fn create_shader(&self, _shader_type: ShaderType, _code: ShaderCode) -> Result<u32, GamekitError> {
+        return Ok(self.allocate_id());
+    }
+
+    fn delete_shader(&self, _id: u32) {
+    }
+
+    fn create_program(&self, _shaders: &[u32]) -> Result<u32, GamekitError> {
+        return Ok(self.allocate_id());
+    }
+
+    fn program_binary(&self, _program: u32) -> Option<(u32, Vec<u8>)> {
+        return None;
+    }
+
+    fn create_program_from_binary(&self, _format: u32, _binary: &[u8]) -> Result<u32, GamekitError> {
+        return Err(GamekitError::invalid_argument("program binaries are not supported by the software backend"));
+    }
+
+    fn driver_id(&self) -> String {
+        return "software".to_string();
+    }
+
+    fn delete_program(&self, _id: u32) {
+    }
+
+    fn use_program(&self, id: u32) {
+        self.state.borrow_mut().current_program = id;
+    }
+
+    fn current_program(&self) -> u32 {
+        return self.state.borrow().current_program;
+    }
+
+    fn program_interface(&self, _program: u32) -> ProgramInterface {
+        return ProgramInterface::default();
+    }
+
+    fn get_uniform_location(&self, _program: u32, _name: &str) -> i32 {
+        return -1;
+    }
+
+    fn set_uniform(&self, _program: u32, _location: i32, _value: UniformValue) {
+    }
+
+    fn set_uniform_array(&self, _program: u32, _location: i32, _values: &[UniformValue]) {
+    }
+
+}
+
+fn find_attribute(vertex_array: &VertexArrayState, location: u32) -> Option<AttributeBinding> {
+    let (attribute, binding) = vertex_array.attributes.iter().find(|(attribute, _)| attribute.location == location)?;
+    let binding = vertex_array.bindings.get(binding).copied().unwrap_or_default();
+    return Some(AttributeBinding { attribute: *attribute, binding });
+}
+
+fn read_attribute(buffers: &HashMap<u32, Vec<u8>>, binding: &AttributeBinding, index: usize, instance: usize, default: [f32; 4]) -> [f32; 4] {
+
+    let mut value = default;
+
+    let data = match buffers.get(&binding.binding.buffer) {
+        Some(data) => data,
+        None => { return value; }
+    };
+
+    let data_type = binding.attribute.data_type;
+    let component_size = data_type.size();
+    let divisor = binding.binding.divisor as usize;
+    let element = if divisor > 0 { instance / divisor } else { index };
+    let base = binding.binding.offset + element * binding.binding.stride + binding.attribute.offset;
+
+    for component in 0..(binding.attribute.components as usize).min(4) {
+        let ofs = base + component * component_size;
+        if ofs + component_size > data.len() {
+            break;
+        }
+        value[component] = match data_type {
+            AttributeType::Float => f32::from_ne_bytes([data[ofs], data[ofs+1], data[ofs+2], data[ofs+3]]),
+            AttributeType::Int => i32::from_ne_bytes([data[ofs], data[ofs+1], data[ofs+2], data[ofs+3]]) as f32,
+            AttributeType::UInt => u32::from_ne_bytes([data[ofs], data[ofs+1], data[ofs+2], data[ofs+3]]) as f32,
+            AttributeType::NormalizedByte => (data[ofs] as i8 as f32 / 127.0).max(-1.0),
+            AttributeType::NormalizedUByte => data[ofs] as f32 / 255.0
+        };
+    }
+
+    return value;
+}
+
+fn rasterize_triangle(state: &mut State, texture_id: u32, a: &Fragment, b: &Fragment, c: &Fragment) {
+
+    // signed area in pixel space (y pointing down): counter-clockwise triangles
+    // in GL window space (y pointing up) have a negative area here
+    let area = edge(a.x, a.y, b.x, b.y, c.x, c.y);
+
+    if area == 0.0 || (state.cull_back_faces && area > 0.0) {
+        return;
+    }
+
+    // bring the triangle into a consistent orientation for the edge tests
+    let (a, b) = if area > 0.0 { (a, b) } else { (b, a) };
+    let area = area.abs();
+
+    // color attachment 0 of the bound render target or the default color buffer
+    let target = state.framebuffers.get(&state.current_framebuffer).map(|attachments| attachments[0]);
+
+    let (width, height) = match target.map(|id| state.textures.get(&id)) {
+        Some(Some(texture)) => (texture.levels[0].width as i32, texture.levels[0].height as i32),
+        Some(None) => { return; },
+        None => (state.width as i32, state.height as i32)
+    };
+
+    let min_x = (a.x.min(b.x).min(c.x).floor() as i32).max(0);
+    let min_y = (a.y.min(b.y).min(c.y).floor() as i32).max(0);
+    let max_x = (a.x.max(b.x).max(c.x).ceil() as i32).min(width - 1);
+    let max_y = (a.y.max(b.y).max(c.y).ceil() as i32).min(height - 1);
+
+    if min_x > max_x || min_y > max_y {
+        return;
+    }
+
+    let texture = state.textures.get(&texture_id);
+    let lod = match texture {
+        Some(texture) => texture_lod(texture, a, b, c, area),
+        None => 0.0
+    };
+
+    let mut fragments: Vec<(usize, usize, [f32; 4])> = Vec::new();
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+
+            let px = x as f32 + 0.5;
+            let py = y as f32 + 0.5;
+
+            let w0 = edge(b.x, b.y, c.x, c.y, px, py);
+            let w1 = edge(c.x, c.y, a.x, a.y, px, py);
+            let w2 = edge(a.x, a.y, b.x, b.y, px, py);
+
+            if !covers(w0, b, c) || !covers(w1, c, a) || !covers(w2, a, b) {
+                continue;
+            }
+
+            let l0 = w0 / area;
+            let l1 = w1 / area;
+            let l2 = w2 / area;
+
+            let mut color = [0.0f32; 4];
+            for i in 0..4 {
+                color[i] = a.color[i] * l0 + b.color[i] * l1 + c.color[i] * l2;
+            }
+
+            if let Some(texture) = texture {
+                let u = a.texcoords[0] * l0 + b.texcoords[0] * l1 + c.texcoords[0] * l2;
+                let v = a.texcoords[1] * l0 + b.texcoords[1] * l1 + c.texcoords[1] * l2;
+                let texel = sample_texture(texture, u, v, lod);
+                for i in 0..4 {
+                    color[i] *= texel[i];
+                }
+            }
+
+            fragments.push((x as usize, y as usize, color));
+        }
+    }
+
+    let blend_mode = state.blend_mode;
+
+    if let Some(target) = target {
+        let level = &mut state.textures.get_mut(&target).unwrap().levels[0];
+        for (x, y, color) in fragments {
+            let texel = &mut level.pixels[(level.height - 1 - y) * level.width + x];
+            // quantize like an RGBA8 attachment
+            *texel = blend_color(*texel, &color, blend_mode).map(|value| from_byte(to_byte(value)));
+        }
+        return;
+    }
+
+    for (x, y, color) in fragments {
+        let ofs = (y * state.width + x) * 4;
+        let pixel = &mut state.color_buffer[ofs..ofs+4];
+        let dest = [from_byte(pixel[0]), from_byte(pixel[1]), from_byte(pixel[2]), from_byte(pixel[3])];
+        pixel.copy_from_slice(&blend_color(dest, &color, blend_mode).map(to_byte));
+    }
+}
+
+fn read_color_buffer(state: &State, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+    for row in 0..height as usize {
+        let src_y = y as usize + row;
+        if src_y >= state.height {
+            break;
+        }
+        let columns = (width as usize).min(state.width.saturating_sub(x as usize));
+        let src = (src_y * state.width + x as usize) * 4;
+        let dest = row * width as usize * 4;
+        pixels[dest..dest + columns * 4].copy_from_slice(&state.color_buffer[src..src + columns * 4]);
+    }
+
+    return pixels;
+}
+
+fn draw_fullscreen(state: &mut State) {
+
+    let texture_id = state.texture_units.get(&0).copied().unwrap_or(0);
+    let target = state.framebuffers.get(&state.current_framebuffer).map(|attachments| attachments[0]);
+
+    let blend_mode = state.blend_mode;
+
+    // sample at pixel centers, v points up like in GL
+    let texels: Vec<[f32; 4]> = {
+        let (width, height) = match target.map(|id| state.textures.get(&id)) {
+            Some(Some(texture)) => (texture.levels[0].width, texture.levels[0].height),
+            Some(None) => { return; },
+            None => (state.width, state.height)
+        };
+
+        let texture = match state.textures.get(&texture_id) {
+            Some(texture) => texture,
+            None => { return; }
+        };
+
+        (0..width * height).map(|index| {
+            let u = ((index % width) as f32 + 0.5) / width as f32;
+            let v = 1.0 - ((index / width) as f32 + 0.5) / height as f32;
+            sample_bilinear(&texture.levels[0], texture.repeat, u, v)
+        }).collect()
+    };
+
+    if let Some(target) = target {
+        let level = &mut state.textures.get_mut(&target).unwrap().levels[0];
+        for (index, color) in texels.iter().enumerate() {
+            let row = level.height - 1 - index / level.width;
+            let texel = &mut level.pixels[row * level.width + index % level.width];
+            *texel = blend_color(*texel, color, blend_mode).map(|value| from_byte(to_byte(value)));
+        }
+        return;
+    }
+
+    for (pixel, color) in state.color_buffer.chunks_exact_mut(4).zip(texels.iter()) {
+        let dest = [from_byte(pixel[0]), from_byte(pixel[1]), from_byte(pixel[2]), from_byte(pixel[3])];
+        pixel.copy_from_slice(&blend_color(dest, color, blend_mode).map(to_byte));
+    }
+}
+
+// rows from top to bottom, like the default color buffer
+fn read_texture(state: &State, id: u32, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+    let level = match state.textures.get(&id) {
+        Some(texture) => &texture.levels[0],
+        None => { return pixels; }
+    };
+
+    for row in 0..height as usize {
+        let src_y = y as usize + row;
+        if src_y >= level.height {
+            break;
+        }
+        let src_row = level.height - 1 - src_y;
+        for column in 0..width as usize {
+            let src_x = x as usize + column;
+            if src_x >= level.width {
+                break;
+            }
+            let texel = level.pixels[src_row * level.width + src_x];
+            let dest = (row * width as usize + column) * 4;
+            pixels[dest..dest+4].copy_from_slice(&texel.map(to_byte));
+        }
+    }
+
+    return pixels;
+}
+
+fn edge(ax: f32, ay: f32, bx: f32, by: f32, px: f32, py: f32) -> f32 {
+    return (bx - ax) * (py - ay) - (by - ay) * (px - ax);
+}
+
+// top-left fill rule, shared edges are only rasterized once
+fn covers(w: f32, from: &Fragment, to: &Fragment) -> bool {
+    if w > 0.0 { return true; }
+    if w < 0.0 { return false; }
+
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+
+    let is_top = dy == 0.0 && dx > 0.0;
+    let is_left = dy < 0.0;
+
+    return is_top || is_left;
+}
+
+fn blend_color(dest: [f32; 4], src: &[f32; 4], blend_mode: BlendMode) -> [f32; 4] {
+
+    let src_alpha = src[3].clamp(0.0, 1.0);
+
+    let mut result = [0.0f32; 4];
+
+    for i in 0..4 {
+        let s = src[i].clamp(0.0, 1.0);
+        let d = dest[i];
+
+        let value = match blend_mode {
+            BlendMode::Normal => s * src_alpha + d * (1.0 - src_alpha),
+            BlendMode::Additive => s * src_alpha + d,
+            BlendMode::Multiply => s * d,
+            BlendMode::Opaque => s
+        };
+
+        result[i] = value.clamp(0.0, 1.0);
+    }
+
+    return result;
+}
+
+fn texture_lod(texture: &TextureImage, a: &Fragment, b: &Fragment, c: &Fragment, area: f32) -> f32 {
+
+    // texture coordinates are affine across the triangle, so the derivatives are constant
+
+    let base = &texture.levels[0];
+    let w = base.width as f32;
+    let h = base.height as f32;
+
+    let du_dx = ((b.texcoords[0] - a.texcoords[0]) * (c.y - a.y) - (c.texcoords[0] - a.texcoords[0]) * (b.y - a.y)) / area;
+    let du_dy = ((c.texcoords[0] - a.texcoords[0]) * (b.x - a.x) - (b.texcoords[0] - a.texcoords[0]) * (c.x - a.x)) / area;
+    let dv_dx = ((b.texcoords[1] - a.texcoords[1]) * (c.y - a.y) - (c.texcoords[1] - a.texcoords[1]) * (b.y - a.y)) / area;
+    let dv_dy = ((c.texcoords[1] - a.texcoords[1]) * (b.x - a.x) - (b.texcoords[1] - a.texcoords[1]) * (c.x - a.x)) / area;
+
+    let rho_x = ((du_dx * w).powi(2) + (dv_dx * h).powi(2)).sqrt();
+    let rho_y = ((du_dy * w).powi(2) + (dv_dy * h).powi(2)).sqrt();
+    let rho = rho_x.max(rho_y);
+
+    if rho <= 1.0 {
+        return 0.0;
+    }
+
+    return rho.log2().min((texture.levels.len() - 1) as f32);
+}
+
+fn sample_texture(texture: &TextureImage, u: f32, v: f32, lod: f32) -> [f32; 4] {
+
+    // GL_LINEAR_MIPMAP_LINEAR minification, GL_LINEAR magnification, GL_REPEAT or GL_CLAMP_TO_EDGE wrapping
+
+    let level0 = lod.floor() as usize;
+    let level1 = (level0 + 1).min(texture.levels.len() - 1);
+    let t = lod - level0 as f32;
+
+    let c0 = sample_bilinear(&texture.levels[level0], texture.repeat, u, v);
+
+    if level0 == level1 || t == 0.0 {
+        return c0;
+    }
+
+    let c1 = sample_bilinear(&texture.levels[level1], texture.repeat, u, v);
+
+    let mut color = [0.0f32; 4];
+    for i in 0..4 {
+        color[i] = c0[i] + (c1[i] - c0[i]) * t;
+    }
+
+    return color;
+}
+
+fn sample_bilinear(level: &MipLevel, repeat: bool, u: f32, v: f32) -> [f32; 4] {
+
+    let x = u * level.width as f32 - 0.5;
+    let y = v * level.height as f32 - 0.5;
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let texel = |tx: i64, ty: i64| -> [f32; 4] {
+        let (tx, ty) = if repeat {
+            (tx.rem_euclid(level.width as i64) as usize, ty.rem_euclid(level.height as i64) as usize)
+        } else {
+            (tx.clamp(0, level.width as i64 - 1) as usize, ty.clamp(0, level.height as i64 - 1) as usize)
+        };
+        return level.pixels[ty * level.width + tx];
+    };
+
+    let x0 = x0 as i64;
+    let y0 = y0 as i64;
+
+    let t00 = texel(x0, y0);
+    let t10 = texel(x0 + 1, y0);
+    let t01 = texel(x0, y0 + 1);
+    let t11 = texel(x0 + 1, y0 + 1);
+
+    let mut color = [0.0f32; 4];
+    for i in 0..4 {
+        let top = t00[i] + (t10[i] - t00[i]) * fx;
+        let bottom = t01[i] + (t11[i] - t01[i]) * fx;
+        color[i] = top + (bottom - top) * fy;
+    }
+
+    return color;
+}
+
+fn generate_mipmaps(base_level: MipLevel) -> Vec<MipLevel> {
+
+    let mut levels = vec![base_level];
+
+    loop {
+        let previous = levels.last().unwrap();
+        if previous.width == 1 && previous.height == 1 {
+            break;
+        }
+
+        let width = (previous.width / 2).max(1);
+        let height = (previous.height / 2).max(1);
+
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (x * 2).min(previous.width - 1);
+                let x1 = (x * 2 + 1).min(previous.width - 1);
+                let y0 = (y * 2).min(previous.height - 1);
+                let y1 = (y * 2 + 1).min(previous.height - 1);
+
+                let mut color = [0.0f32; 4];
+                for (sx, sy) in [(x0, y0), (x1, y0), (x0, y1), (x1, y1)] {
+                    let texel = previous.pixels[sy * previous.width + sx];
+                    for i in 0..4 {
+                        color[i] += texel[i] * 0.25;
+                    }
+                }
+
+                pixels.push(color);
+            }
+        }
+
+        levels.push(MipLevel { width, height, pixels });
+    }
+
+    return levels;
+}
+
+fn from_byte(value: u8) -> f32 {
+    return value as f32 / 255.0;
+}
+
+fn to_byte(value: f32) -> u8 {
+    return (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SoftwareBackend;
+    use crate::graphics::{
+        api::BlendMode,
+        backend::{Backend, TextureFormat},
+        buffers::BufferUsage,
+        primitives::DrawMode,
+        types::Vertex,
+        vertex_layout::VertexLayout
+    };
+
+    const SIZE: u32 = 4;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    fn create_backend() -> SoftwareBackend {
+        let backend = SoftwareBackend::new(SIZE, SIZE);
+        backend.initialize();
+        backend.clear_color(0.0, 0.0, 0.0, 0.0);
+        backend.clear();
+        return backend;
+    }
+
+    fn bind_texture(backend: &SoftwareBackend, width: u32, height: u32, texels: &[[u8; 4]]) {
+        let id = backend.create_texture(width, height, TextureFormat::Rgba8, texels.concat().as_slice()).unwrap();
+        backend.bind_texture(id, 0);
+    }
+
+    // rectangle x0, y0, x1, y1 in pixels from the top left, texture coordinates u0, v0, u1, v1
+    fn draw_quad(backend: &SoftwareBackend, rectangle: [f32; 4], texture_coords: [f32; 4], color: [f32; 4]) {
+
+        let [x0, y0, x1, y1] = rectangle;
+        let [u0, v0, u1, v1] = texture_coords;
+
+        // two triangles sharing the diagonal, front facing in GL window space
+        let corners = [(x0, y0, u0, v0), (x0, y1, u0, v1), (x1, y1, u1, v1), (x0, y0, u0, v0), (x1, y1, u1, v1), (x1, y0, u1, v0)];
+
+        // Vertex layout: position xyz, color rgba, texture coordinates uv
+        let data: Vec<u8> = corners.iter()
+            .flat_map(|(x, y, u, v)| [*x, *y, 0.0, color[0], color[1], color[2], color[3], *u, *v])
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+
+        let vertex_array = backend.create_vertex_array();
+        for attribute in Vertex::attributes() {
+            backend.vertex_array_attribute(vertex_array, 0, &attribute);
+        }
+
+        let buffer = backend.create_buffer();
+        backend.buffer_data(buffer, &BufferUsage::StaticDraw, &data);
+        backend.vertex_array_buffer(vertex_array, 0, buffer, 0, std::mem::size_of::<Vertex>(), 0);
+
+        backend.bind_vertex_array(vertex_array);
+        backend.draw_arrays(DrawMode::Triangles, 0, corners.len());
+    }
+
+    fn pixels(backend: &SoftwareBackend) -> Vec<[u8; 4]> {
+        return backend.read_pixels(0, 0, SIZE, SIZE, SIZE).chunks_exact(4).map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]]).collect();
+    }
+
+    fn full_quad(backend: &SoftwareBackend, texture_coords: [f32; 4], color: [f32; 4]) {
+        draw_quad(backend, [0.0, 0.0, SIZE as f32, SIZE as f32], texture_coords, color);
+    }
+
+    // 4x4 texture, one color per column
+    fn column_texels() -> Vec<[u8; 4]> {
+        return (0..16).map(|index| [RED, GREEN, BLUE, WHITE][index % 4]).collect();
+    }
+
+    #[test]
+    fn texel_centers_map_to_pixel_centers() {
+        let backend = create_backend();
+        let texels: Vec<[u8; 4]> = (0..16u8).map(|index| [index * 16, 255 - index * 16, index, 255]).collect();
+        bind_texture(&backend, SIZE, SIZE, &texels);
+        backend.set_blend_mode(BlendMode::Opaque);
+        full_quad(&backend, [0.0, 0.0, 1.0, 1.0], [1.0; 4]);
+        assert_eq!(pixels(&backend), texels);
+    }
+
+    #[test]
+    fn shared_edges_are_filled_once() {
+        let backend = create_backend();
+        bind_texture(&backend, 1, 1, &[WHITE]);
+        backend.set_blend_mode(BlendMode::Additive);
+
+        // left and top edges run through pixel centers and are included, right and bottom ones are not
+        draw_quad(&backend, [0.5, 0.5, 2.5, 2.5], [0.0, 0.0, 1.0, 1.0], [0.25, 0.25, 0.25, 1.0]);
+
+        let covered = [64, 64, 64, 255];
+        let empty = [0, 0, 0, 0];
+        assert_eq!(pixels(&backend), vec![
+            covered, covered, empty, empty,
+            covered, covered, empty, empty,
+            empty, empty, empty, empty,
+            empty, empty, empty, empty
+        ]);
+    }
+
+    #[test]
+    fn blend_modes() {
+        let source = [1.0, 0.6, 0.25, 0.25];
+
+        // destination 51, 102, 153, 255
+        let cases = [
+            (BlendMode::Normal, [102, 115, 131, 207]),
+            (BlendMode::Additive, [115, 140, 169, 255]),
+            (BlendMode::Multiply, [51, 61, 38, 64]),
+            (BlendMode::Opaque, [255, 153, 64, 64])
+        ];
+
+        for (blend_mode, expected) in cases {
+            let backend = create_backend();
+            backend.clear_color(0.2, 0.4, 0.6, 1.0);
+            backend.clear();
+            bind_texture(&backend, 1, 1, &[WHITE]);
+            backend.set_blend_mode(blend_mode);
+            full_quad(&backend, [0.0, 0.0, 1.0, 1.0], source);
+            assert_eq!(pixels(&backend), vec![expected; 16], "{:?}", blend_mode);
+        }
+    }
+
+    #[test]
+    fn texture_coordinates_repeat() {
+        let backend = create_backend();
+        bind_texture(&backend, SIZE, SIZE, &column_texels());
+        backend.set_blend_mode(BlendMode::Opaque);
+
+        // shifted by half the texture, the right half wraps around to the first columns
+        full_quad(&backend, [0.5, 0.0, 1.5, 1.0], [1.0; 4]);
+
+        let row = [BLUE, WHITE, RED, GREEN];
+        assert_eq!(pixels(&backend), row.repeat(4));
+    }
+
+    #[test]
+    fn magnification_is_linear() {
+        let backend = create_backend();
+        let black = [0, 0, 0, 255];
+        bind_texture(&backend, 2, 2, &[black, WHITE, black, WHITE]);
+        backend.set_blend_mode(BlendMode::Opaque);
+
+        // u from the center of the black texel to the center of the white one
+        full_quad(&backend, [0.25, 0.0, 0.75, 1.0], [1.0; 4]);
+
+        let row: Vec<[u8; 4]> = [32, 96, 159, 223].iter().map(|value| [*value, *value, *value, 255]).collect();
+        assert_eq!(pixels(&backend), row.repeat(4));
+    }
+
+}
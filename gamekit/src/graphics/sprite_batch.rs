@@ -2,26 +2,36 @@
 // Sprite batch
 //
 
-use super::{types::{Vertex}, texture::Texture, primitives::{self, Primitives}, api::Api, buffers::{VertexArray, BufferObject, BufferUsage, BufferType}, sprite::Sprite};
+use super::{types::{Vertex, Rectangle}, texture::Texture, render_target::RenderTarget, primitives::{self, Primitives}, api::Api, buffers::{VertexArray, RingBuffer, BufferUsage}, sprite::Sprite, error::GamekitError};
 
 defaults!();
 
+// Sprites are written straight into a persistently mapped ring buffer,
+// each begin moves on to a region the GPU is done with
 pub struct SpriteBatch {
     texture_id: u32,
-    vertices: Vec<Vertex>,
+    // area of the texture the sprite texture coordinates refer to
+    texture_coords: Rectangle,
+    vertex_buffer: RingBuffer<Vertex>,
     _indices: Vec<u32>,
-    vertex_array: VertexArray,
-    index_buffer: BufferObject,
+    vertex_array: VertexArray<Vertex>,
     size: usize,
-    modified: bool,
     count: usize
 }
 
 impl SpriteBatch {
 
-    pub fn new<'b>(texture: &Texture, size: usize) -> Result<SpriteBatch, String> {
+    pub fn new<'b>(texture: &Texture, size: usize) -> Result<SpriteBatch, GamekitError> {
+        return Self::create(texture.id(), Rectangle::new(0.0, 0.0, 1.0, 1.0), size);
+    }
+
+    // draws from color attachment 0 of the target, sprite texture
+    // coordinates have v = 0 at the top as for ordinary textures
+    pub fn with_render_target(target: &RenderTarget, size: usize) -> Result<SpriteBatch, GamekitError> {
+        return Self::create(target.texture().id(), target.texture_coords(), size);
+    }
 
-        let vertices = vec![Vertex::zero(); size * 4];
+    fn create(texture_id: u32, texture_coords: Rectangle, size: usize) -> Result<SpriteBatch, GamekitError> {
 
         let mut indices = vec![0; size * 6];
 
@@ -41,48 +51,40 @@ impl SpriteBatch {
             ofs += 4;
         }
 
-        let mut vertex_array = VertexArray::new(BufferUsage::StaticDraw, 0)?;
-        vertex_array.copy_vertices_to_buffer(&vertices, vertices.len());
+        let vertex_buffer = RingBuffer::new(size * 4)?;
 
-        let index_buffer = BufferObject::new(BufferType::IndexBuffer, BufferUsage::StaticDraw)?;
-        index_buffer.copy_u32_to_buffer(&indices, indices.len());
+        let mut vertex_array = VertexArray::with_ring_buffer(&vertex_buffer, 0)?;
+        vertex_array.set_indices(&indices, BufferUsage::StaticDraw)?;
 
         let sprite_batch = SpriteBatch {
-            texture_id: texture.id(),
-            vertices,
+            texture_id,
+            texture_coords,
+            vertex_buffer,
             _indices: indices,
             vertex_array,
-            index_buffer,
             size,
-            modified: false,
             count: 0
         };
 
         return Ok(sprite_batch);
     }
 
-    pub fn update(&mut self) {
-        if !self.modified { return; }
-        if self.count > 0 {
-            self.vertex_array.copy_vertices_to_buffer(&self.vertices, self.count * 4);
-        }
-        self.modified = false;
-    }
-
     pub fn begin(&mut self) {
         self.count = 0;
+        self.vertex_buffer.begin();
+        self.vertex_array.set_ring_region(&self.vertex_buffer);
     }
 
+    // the vertices are already in the buffer, nothing to upload
     pub fn end(&mut self) {
-        self.update();
     }
 
-    pub fn push(&mut self, sprite: &dyn Sprite) {
-        if self.count >= self.size { panic!("sprite batch overflow"); }
+    // returns false and drops the sprite when the batch is full
+    pub fn push(&mut self, sprite: &dyn Sprite) -> bool {
+        if self.count >= self.size { return false; }
 
         let index = self.count as u32;
         self.count += 1;
-        self.modified = true;
 
         let data = sprite.get_sprite_data();
 
@@ -92,52 +94,33 @@ impl SpriteBatch {
         let y1 = y0 + data.size.y;
         let z = 0.0f32;
 
-        let u0 = data.texture_coords.x;
-        let v0 = data.texture_coords.y;
-        let u1 = u0 + data.texture_coords.w;
-        let v1 = v0 + data.texture_coords.h;
+        let area = self.texture_coords;
+        let u0 = area.x + data.texture_coords.x * area.w;
+        let v0 = area.y + data.texture_coords.y * area.h;
+        let u1 = u0 + data.texture_coords.w * area.w;
+        let v1 = v0 + data.texture_coords.h * area.h;
 
-        let red = data.color.red;
-        let green = data.color.green;
-        let blue = data.color.blue;
-        let alpha = data.color.alpha;
+        let color = data.color;
 
         let ofs = index as usize * 4;
 
-        {
-            let vertex = &mut self.vertices[ofs+0];
-            vertex.coords.set(x0, y0, z);
-            vertex.color.set(red, green, blue, alpha);
-            vertex.texcoords.set(u0, v0);
-        }
-
-        {
-            let vertex = &mut self.vertices[ofs+1];
-            vertex.coords.set(x1, y0, z);
-            vertex.color.set(red, green, blue, alpha);
-            vertex.texcoords.set(u1, v0);
-        }
-
-        {
-            let vertex = &mut self.vertices[ofs+2];
-            vertex.coords.set(x1, y1, z);
-            vertex.color.set(red, green, blue, alpha);
-            vertex.texcoords.set(u1, v1);
-        }
-
-        {
-            let vertex = &mut self.vertices[ofs+3];
-            vertex.coords.set(x0, y1, z);
-            vertex.color.set(red, green, blue, alpha);
-            vertex.texcoords.set(u0, v1);
-        }
+        // whole vertices are stored, mapped memory is not read back
+        let vertices = &mut self.vertex_buffer.region()[ofs..ofs+4];
+        vertices[0] = Vertex::new(x0, y0, z, color, u0, v0);
+        vertices[1] = Vertex::new(x1, y0, z, color, u1, v0);
+        vertices[2] = Vertex::new(x1, y1, z, color, u1, v1);
+        vertices[3] = Vertex::new(x0, y1, z, color, u0, v1);
 
+        return true;
     }
 
+    // pushes sprites[offset..offset+count], clamped to the sprites given
     pub fn push_vec(&mut self, sprites: Vec<&dyn Sprite>, offset: usize, count: usize) {
-        assert!(offset+count < sprites.len());
-        for i in offset..(offset+count) {
-            self.push(sprites[i]);
+        let end = offset.saturating_add(count).min(sprites.len());
+        for sprite in sprites.iter().take(end).skip(offset) {
+            if !self.push(*sprite) {
+                break;
+            }
         }
     }
 
@@ -145,8 +128,11 @@ impl SpriteBatch {
         self.draw_buffered(self.count, api);
     }
 
+    // draws the first count sprites pushed since begin
     pub fn draw_buffered(&self, count: usize, api: &mut dyn Api) {
 
+        let count = count.min(self.count);
+
         if 0 == count {
             return;
         }
@@ -154,12 +140,12 @@ impl SpriteBatch {
         Primitives::bind_texture(self.texture_id, 0);
 
         self.vertex_array.bind();
-        self.index_buffer.bind();
 
         api.draw_elements(primitives::DrawMode::Triangles, count * 6);
 
         self.vertex_array.unbind();
-        self.index_buffer.unbind();
+
+        self.vertex_buffer.fence();
 
     }
 
@@ -168,3 +154,115 @@ impl SpriteBatch {
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::SpriteBatch;
+    use crate::graphics::{
+        backend::TextureFormat,
+        buffers::RING_BUFFER_REGIONS,
+        graphics::Graphics,
+        primitives::DrawMode,
+        recording::{Command, RecordingBackend},
+        sprite::{Sprite, SpriteData},
+        texture::Texture,
+        types::{Color, Rectangle, Vector2, Vertex}
+    };
+
+    const CAPACITY: usize = 8;
+
+    struct TestSprite(SpriteData);
+
+    impl Sprite for TestSprite {
+        fn get_sprite_data(&self) -> &SpriteData {
+            return &self.0;
+        }
+    }
+
+    fn test_sprite() -> TestSprite {
+        return TestSprite(SpriteData {
+            position: Vector2::new(1.0, 2.0),
+            size: Vector2::new(4.0, 4.0),
+            color: Color::new(1.0, 1.0, 1.0, 1.0),
+            texture_coords: Rectangle::new(0.0, 0.0, 1.0, 1.0)
+        });
+    }
+
+    #[test]
+    fn frames_cycle_through_the_ring_regions() {
+        let backend = Rc::new(RecordingBackend::new());
+        let mut graphics = Graphics::with_backend(backend.clone(), 64, 64);
+        let texture = Texture::with_size(4, 4, TextureFormat::Rgba8).unwrap();
+        let mut batch = SpriteBatch::new(&texture, CAPACITY).unwrap();
+        let sprite = test_sprite();
+
+        let ring = batch.vertex_buffer.id();
+        let region_size = CAPACITY * 4 * std::mem::size_of::<Vertex>();
+        let mut fences = Vec::new();
+
+        for frame in 0..RING_BUFFER_REGIONS * 2 {
+            backend.clear_commands();
+
+            batch.begin();
+            for _ in 0..=frame {
+                batch.push(&sprite);
+            }
+            batch.end();
+            batch.draw(&mut graphics);
+
+            let commands = backend.take_commands();
+
+            let region = commands.iter().position(|command| matches!(command, Command::VertexArrayBuffer { buffer, .. } if *buffer == ring)).unwrap();
+            assert!(matches!(commands[region], Command::VertexArrayBuffer { offset, .. } if offset == (frame % RING_BUFFER_REGIONS) * region_size));
+
+            // the region is reused once the draws of RING_BUFFER_REGIONS frames ago are complete
+            let wait = commands.iter().position(|command| matches!(command, Command::WaitFence {..}));
+            if frame < RING_BUFFER_REGIONS {
+                assert_eq!(wait, None);
+            } else {
+                let fence = fences[frame - RING_BUFFER_REGIONS];
+                let wait = wait.unwrap();
+                assert_eq!(commands[wait], Command::WaitFence { fence });
+                assert_eq!(commands[wait + 1], Command::DeleteFence { fence });
+                assert!(wait < region);
+            }
+
+            let draw = commands.iter().position(|command| matches!(command, Command::DrawElements {..})).unwrap();
+            assert!(region < draw);
+            assert_eq!(commands[draw], Command::DrawElements { mode: DrawMode::Triangles, count: (frame + 1) * 6 });
+
+            let sync = commands.iter().position(|command| matches!(command, Command::FenceSync {..})).unwrap();
+            assert!(draw < sync);
+            if let Command::FenceSync { fence } = commands[sync] {
+                fences.push(fence);
+            }
+        }
+
+        assert_eq!(fences.len(), RING_BUFFER_REGIONS * 2);
+    }
+
+    #[test]
+    fn buffered_draws_are_clamped_to_the_pushed_sprites() {
+        let backend = Rc::new(RecordingBackend::new());
+        let mut graphics = Graphics::with_backend(backend.clone(), 64, 64);
+        let texture = Texture::with_size(4, 4, TextureFormat::Rgba8).unwrap();
+        let mut batch = SpriteBatch::new(&texture, CAPACITY).unwrap();
+        let sprite = test_sprite();
+
+        batch.begin();
+        batch.push(&sprite);
+        batch.push(&sprite);
+        batch.end();
+
+        backend.clear_commands();
+        batch.draw_buffered(1, &mut graphics);
+        batch.draw_buffered(CAPACITY * 2, &mut graphics);
+
+        assert_eq!(backend.draw_calls(), vec![
+            Command::DrawElements { mode: DrawMode::Triangles, count: 6 },
+            Command::DrawElements { mode: DrawMode::Triangles, count: 12 }
+        ]);
+    }
+
+}
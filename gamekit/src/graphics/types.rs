@@ -2,11 +2,11 @@
 // Vertex
 //
 
-use super::gl;
+use super::vertex_layout::VertexLayout;
 
 defaults!();
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C, packed)]
 pub struct Vector2 {
     pub x: f32,
@@ -22,7 +22,7 @@ impl Vector2 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C, packed)]
 pub struct Vector2i {
     pub x: i32,
@@ -38,7 +38,7 @@ impl Vector2i {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C, packed)]
 pub struct Vector3 {
     pub x: f32,
@@ -56,7 +56,7 @@ impl Vector3 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C, packed)]
 pub struct Vector4 {
     pub x: f32,
@@ -76,6 +76,40 @@ impl Vector4 {
     }
 }
 
+// matrices are column major like in GLSL
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct Matrix2 {
+    pub columns: [[f32; 2]; 2]
+}
+
+impl Matrix2 {
+    pub fn new(columns: [[f32; 2]; 2]) -> Self { Self { columns } }
+    pub fn identity() -> Self { Self::new([[1.0, 0.0], [0.0, 1.0]]) }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct Matrix3 {
+    pub columns: [[f32; 3]; 3]
+}
+
+impl Matrix3 {
+    pub fn new(columns: [[f32; 3]; 3]) -> Self { Self { columns } }
+    pub fn identity() -> Self { Self::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]) }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct Matrix4 {
+    pub columns: [[f32; 4]; 4]
+}
+
+impl Matrix4 {
+    pub fn new(columns: [[f32; 4]; 4]) -> Self { Self { columns } }
+    pub fn identity() -> Self { Self::new([[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]) }
+}
 
 #[derive(Copy, Clone, Debug)]
 #[repr(C, packed)]
@@ -191,14 +225,14 @@ impl Quad {
     }
 
 }
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, VertexLayout)]
 #[repr(C, packed)]
 pub struct Vertex {
-    //#[location = "0"]
+    #[location = 0]
     pub coords: Vector3,
-    //#[location = "1"]
+    #[location = 1]
     pub color: Color,
-    //#[location = "2"]
+    #[location = 2]
     pub texcoords: Vector2
 }
 
@@ -220,29 +254,6 @@ impl Vertex {
         }
     }
 
-    pub fn declare_attrib_pointers(start_index: u32) {
-
-        let mut index = start_index as gl::types::GLuint;
-        let mut offset = 0 as gl::types::GLuint;
-        let stride = std::mem::size_of::<Vertex>() as gl::types::GLsizei;
-        let float_size = std::mem::size_of::<f32>() as u32;
-
-        unsafe {
-            gl::EnableVertexAttribArray(index);
-            gl::VertexAttribPointer(index, 3, gl::FLOAT, gl::FALSE, stride, offset as *const gl::types::GLvoid);
-            index += 1; offset += 3 * float_size;
-
-            gl::VertexAttribPointer(index, 4, gl::FLOAT, gl::FALSE, stride, offset as *const gl::types::GLvoid);
-            gl::EnableVertexAttribArray(index);
-            index += 1; offset += 4 * float_size;
-
-            gl::VertexAttribPointer(index, 2, gl::FLOAT, gl::FALSE, stride, offset as *const gl::types::GLvoid);
-            gl::EnableVertexAttribArray(index);
-            //index += 1; offset += 2 * float_size;
-        };
-
-    }
-
     pub fn set_color(&mut self, color: &Color) {
         self.color.set_color(color);
     }
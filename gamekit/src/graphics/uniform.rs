@@ -2,161 +2,162 @@
 // Shader Uniforms
 //
 
-use std::{ffi::CString, marker::PhantomData};
+use std::{collections::HashMap, marker::PhantomData};
 
-use crate::graphics::{program::Program};
+use crate::graphics::{program::{self, Program}};
 
-use super::{gl, types::{Vector2i, Vector4, Vector3, Vector2}};
+use super::{backend::{self, UniformValue}, types::{Vector2i, Vector4, Vector3, Vector2, Matrix2, Matrix3, Matrix4}};
 
 defaults!();
 
-pub struct Uniform<T> {
+// Uniform by name. set_for writes to the given program at the location its
+// reflected interface reports, set writes to the current program and looks
+// the location up once per program:
+//
+//     let mut transform: Uniform<Matrix4> = Uniform::new("transform");
+//     let lights: Uniform<[Vector3]> = Uniform::new("lights");
+//     transform.set(Matrix4::identity());
+//     lights.set_for(&program, &[Vector3::zero(), Vector3::zero()]);
+pub struct Uniform<T: ?Sized> {
     name: String,
-    location: i32,
+    // location per program id
+    locations: HashMap<u32, i32>,
+    // program ids are reused after a program is deleted, the locations are queried again then
+    generation: u64,
     phantom: PhantomData<T>
 }
 
-impl<T> Uniform<T> {
+impl<T: ?Sized> Uniform<T> {
 
     pub fn new(name: &str) -> Uniform<T> {
 
         let uniform = Uniform {
             name: name.to_string(),
-            location: -1,
+            locations: HashMap::new(),
+            generation: program::program_generation(),
             phantom: PhantomData
         };
 
         return uniform;
     }
 
-    fn get_uniform_location(name: &str) -> i32 {
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    // current program and the location in it
+    fn get_location(&mut self) -> (u32, i32) {
 
         let program_id = Program::get_current_program();
         if program_id == 0 {
-            return -1;
+            return (0, -1);
         }
 
-        let cname = CString::new(name).expect("expected uniform name to have no nul bytes");
+        let generation = program::program_generation();
+        if generation != self.generation {
+            self.locations.clear();
+            self.generation = generation;
+        }
 
-        let location: i32;
+        let name = &self.name;
+        let location = *self.locations.entry(program_id).or_insert_with(|| backend::current().get_uniform_location(program_id, name));
+        return (program_id, location);
+    }
 
-        unsafe {
-            location = gl::GetUniformLocation(program_id as gl::types::GLuint, cname.as_bytes_with_nul().as_ptr() as *const i8) as i32;
-        }
+}
 
-        return location;
+// texture unit a sampler reads from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureUnit(pub u32);
 
-    }
+// values that can be stored in uniforms
+pub trait UniformType: Copy {
+    fn uniform_value(self) -> UniformValue;
+}
 
-    fn get_location(&mut self) -> i32 {
+impl UniformType for f32 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Float(self) }
+}
 
-        if self.location < 0 {
-            self.location = Self::get_uniform_location(&self.name);
-        }
+impl UniformType for Vector2 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Vec2(self) }
+}
 
-        return self.location;
-    }
+impl UniformType for Vector3 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Vec3(self) }
+}
 
+impl UniformType for Vector4 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Vec4(self) }
 }
 
-pub trait Uniform1f {
-    fn set(&mut self, value: f32);
+impl UniformType for i32 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Int(self) }
 }
 
-impl Uniform1f for Uniform<f32> {
-    fn set(&mut self, value: f32) {
-        let location = self.get_location();
-        if location >= 0 {
-            unsafe {
-                gl::Uniform1f(location as gl::types::GLint, value as gl::types::GLfloat);
-            }
-        }
-    }
+impl UniformType for Vector2i {
+    fn uniform_value(self) -> UniformValue { UniformValue::IVec2(self) }
 }
 
-pub trait Uniform2f {
-    fn set(&mut self, value: Vector2);
+impl UniformType for u32 {
+    fn uniform_value(self) -> UniformValue { UniformValue::UInt(self) }
 }
 
-impl Uniform2f for Uniform<Vector2> {
-    fn set(&mut self, value: Vector2) {
-        let location = self.get_location();
-        if location >= 0 {
-            unsafe {
-                gl::Uniform2f(location as gl::types::GLint,
-                              value.x as gl::types::GLfloat,
-                              value.y as gl::types::GLfloat);
-            }
-        }
-    }
+impl UniformType for bool {
+    fn uniform_value(self) -> UniformValue { UniformValue::Bool(self) }
 }
 
-pub trait Uniform3f {
-    fn set(&mut self, value: Vector3);
+impl UniformType for Matrix2 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Mat2(self) }
 }
 
-impl Uniform3f for Uniform<Vector3> {
-    fn set(&mut self, value: Vector3) {
-        let location = self.get_location();
-        if location >= 0 {
-            unsafe {
-                gl::Uniform3f(location as gl::types::GLint,
-                              value.x as gl::types::GLfloat,
-                              value.y as gl::types::GLfloat,
-                              value.z as gl::types::GLfloat);
-            }
-        }
-    }
+impl UniformType for Matrix3 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Mat3(self) }
+}
+
+impl UniformType for Matrix4 {
+    fn uniform_value(self) -> UniformValue { UniformValue::Mat4(self) }
 }
 
-pub trait Uniform4f {
-    fn set(&mut self, value: Vector4);
+impl UniformType for TextureUnit {
+    fn uniform_value(self) -> UniformValue { UniformValue::Sampler(self.0) }
 }
 
-impl Uniform4f for Uniform<Vector4> {
-    fn set(&mut self, value: Vector4) {
-        let location = self.get_location();
+impl<T: UniformType> Uniform<T> {
+    pub fn set(&mut self, value: T) {
+        let (program_id, location) = self.get_location();
         if location >= 0 {
-            unsafe {
-                gl::Uniform4f(location as gl::types::GLint,
-                              value.x as gl::types::GLfloat,
-                              value.y as gl::types::GLfloat,
-                              value.z as gl::types::GLfloat,
-                              value.w as gl::types::GLfloat);
-            }
+            backend::current().set_uniform(program_id, location, value.uniform_value());
         }
     }
-}
 
-pub trait Uniform1i {
-    fn set(&mut self, value: i32);
+    // inactive uniforms are not set
+    pub fn set_for(&self, program: &Program, value: T) {
+        if let Some(location) = program.uniform_location(&self.name) {
+            backend::current().set_uniform(program.id(), location, value.uniform_value());
+        }
+    }
 }
 
-impl Uniform1i for Uniform<i32> {
-    fn set(&mut self, value: i32) {
-        let location = self.get_location();
+// sets the array from its first element on, the name may be given with or without [0]
+impl<T: UniformType> Uniform<[T]> {
+    pub fn set(&mut self, values: &[T]) {
+        let (program_id, location) = self.get_location();
         if location >= 0 {
-            unsafe {
-                gl::Uniform1i(location as gl::types::GLint,
-                              value as gl::types::GLint);
-            }
+            set_array(program_id, location, values);
         }
     }
-}
 
-pub trait Uniform2i {
-    fn set(&mut self, value: Vector2i);
+    pub fn set_for(&self, program: &Program, values: &[T]) {
+        if let Some(location) = program.uniform_location(&self.name) {
+            set_array(program.id(), location, values);
+        }
+    }
 }
 
-impl Uniform2i for Uniform<Vector2i> {
-    fn set(&mut self, value: Vector2i) {
-        let location = self.get_location();
-        if location >= 0 {
-            unsafe {
-                gl::Uniform2i(location as gl::types::GLint,
-                              value.x as gl::types::GLint,
-                              value.y as gl::types::GLint);
-            }
-        }
+fn set_array<T: UniformType>(program_id: u32, location: i32, values: &[T]) {
+    if !values.is_empty() {
+        let values: Vec<UniformValue> = values.iter().map(|value| value.uniform_value()).collect();
+        backend::current().set_uniform_array(program_id, location, &values);
     }
 }
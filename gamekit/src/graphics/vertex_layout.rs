@@ -0,0 +1,89 @@
+//
+// Vertex Layout
+//
+
+// Attribute layout of vertex structs, derived from the fields:
+//
+//     #[derive(Copy, Clone, VertexLayout)]
+//     #[repr(C)]
+//     struct MeshVertex {
+//         #[location = 0]
+//         position: Vector3,
+//         #[location = 3]
+//         normal: [i8; 4],
+//         color: [u8; 4]
+//     }
+//
+// Fields without a location use the one after the previous field. f32 and
+// vectors are float attributes, i32/u32 and their arrays integer attributes,
+// [u8; N] and [i8; N] are normalized to [0, 1] and [-1, 1].
+
+use super::{
+    backend::{self, AttributeType, VertexAttribute},
+    types::{Vector2, Vector2i, Vector3, Vector4, Color}
+};
+
+pub use gamekit_derive::VertexLayout;
+
+pub trait VertexLayout: Copy {
+
+    // attributes of one vertex, consecutive vertices are size_of::<Self>() apart
+    fn attributes() -> Vec<VertexAttribute>;
+
+    // declares the attributes of a vertex array reading from buffer binding slot
+    // binding, locations are shifted by start_location
+    fn declare_attributes(vertex_array: u32, binding: u32, start_location: u32) {
+
+        let backend = backend::current();
+
+        for attribute in Self::attributes() {
+            let attribute = VertexAttribute { location: start_location + attribute.location, ..attribute };
+            backend.vertex_array_attribute(vertex_array, binding, &attribute);
+        }
+    }
+
+}
+
+// types that can be vertex struct fields
+pub trait VertexComponent {
+    const DATA_TYPE: AttributeType;
+    const COMPONENTS: u32;
+}
+
+// compile time check of derived layouts, attributes have one to four components
+pub const fn check_component<T: VertexComponent>() {
+    assert!(T::COMPONENTS >= 1 && T::COMPONENTS <= 4, "vertex attributes have one to four components");
+}
+
+macro_rules! vertex_component {
+    ($type:ty, $data_type:expr, $components:expr) => {
+        impl VertexComponent for $type {
+            const DATA_TYPE: AttributeType = $data_type;
+            const COMPONENTS: u32 = $components;
+        }
+    }
+}
+
+vertex_component!(f32, AttributeType::Float, 1);
+vertex_component!(Vector2, AttributeType::Float, 2);
+vertex_component!(Vector3, AttributeType::Float, 3);
+vertex_component!(Vector4, AttributeType::Float, 4);
+vertex_component!(Color, AttributeType::Float, 4);
+vertex_component!(i32, AttributeType::Int, 1);
+vertex_component!(Vector2i, AttributeType::Int, 2);
+vertex_component!(u32, AttributeType::UInt, 1);
+
+macro_rules! vertex_component_array {
+    ($type:ty, $data_type:expr) => {
+        impl<const N: usize> VertexComponent for [$type; N] {
+            const DATA_TYPE: AttributeType = $data_type;
+            const COMPONENTS: u32 = N as u32;
+        }
+    }
+}
+
+vertex_component_array!(f32, AttributeType::Float);
+vertex_component_array!(i32, AttributeType::Int);
+vertex_component_array!(u32, AttributeType::UInt);
+vertex_component_array!(i8, AttributeType::NormalizedByte);
+vertex_component_array!(u8, AttributeType::NormalizedUByte);
@@ -0,0 +1,18 @@
+//
+// Gamekit
+//
+
+#[macro_export]
+macro_rules! defaults {
+    () => {
+        #[allow(unused_imports)]
+        use log::{debug, info, trace, warn, error};
+    }
+}
+
+// lets derived code refer to ::gamekit inside this crate too
+extern crate self as gamekit;
+
+pub mod graphics;
+
+pub use graphics::{application, api, error::GamekitError};
@@ -0,0 +1,160 @@
+//
+// Gamekit Derive Macros
+//
+
+// Derives for the traits in gamekit::graphics, re-exported there. The
+// generated code refers to the gamekit crate by name.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DataStruct, DeriveInput, Expr, ExprLit, Field, Fields, FieldsNamed, Lit};
+
+// #[derive(BlockLayout)] lays out the fields in declaration order following the
+// std140 and std430 rules, padding is inserted when the value is written
+#[proc_macro_derive(BlockLayout)]
+pub fn block_layout_derive(input: TokenStream) -> TokenStream {
+
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    let fields = match named_fields(&ast) {
+        Ok(fields) => fields,
+        Err(error) => { return error.to_compile_error().into(); }
+    };
+
+    let name = &ast.ident;
+
+    let idents: Vec<_> = fields.named.iter().map(|field| field.ident.clone().unwrap()).collect();
+    let names: Vec<String> = idents.iter().map(|ident| ident.to_string()).collect();
+    let types: Vec<_> = fields.named.iter().map(|field| field.ty.clone()).collect();
+    let indices: Vec<usize> = (0..fields.named.len()).collect();
+
+    let layout = quote!(::gamekit::graphics::layout);
+    let misaligned = format!("misaligned member in block {}", name);
+
+    let generated = quote! {
+
+        impl #layout::BlockLayout for #name {
+
+            const STD140: #layout::TypeLayout = #layout::struct_layout(#layout::Layout::Std140, &[#(<#types as #layout::BlockLayout>::STD140),*]);
+            const STD430: #layout::TypeLayout = #layout::struct_layout(#layout::Layout::Std430, &[#(<#types as #layout::BlockLayout>::STD430),*]);
+
+            fn write(&self, layout: #layout::Layout, out: &mut [u8]) {
+                let members = [#(layout.of::<#types>()),*];
+                #(
+                    #layout::BlockLayout::write(&self.#idents, layout, &mut out[#layout::member_offset(layout, &members, #indices)..]);
+                )*
+            }
+
+            fn members(layout: #layout::Layout) -> Vec<(String, usize)> {
+                let members = [#(layout.of::<#types>()),*];
+                let mut names = Vec::new();
+                #(
+                    names.extend(#layout::nested_members::<#types>(layout, #names, #layout::member_offset(layout, &members, #indices)));
+                )*
+                return names;
+            }
+
+        }
+
+        // every member has to start at a multiple of its base alignment
+        const _: () = {
+            assert!(#layout::check_struct_layout(#layout::Layout::Std140, &[#(<#types as #layout::BlockLayout>::STD140),*]), #misaligned);
+            assert!(#layout::check_struct_layout(#layout::Layout::Std430, &[#(<#types as #layout::BlockLayout>::STD430),*]), #misaligned);
+        };
+
+    };
+
+    return generated.into();
+}
+
+fn named_fields(ast: &DeriveInput) -> Result<&FieldsNamed, syn::Error> {
+
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&ast.generics, "generic structs cannot be derived"));
+    }
+
+    return match &ast.data {
+        Data::Struct(DataStruct { fields: Fields::Named(fields), .. }) => Ok(fields),
+        _ => Err(syn::Error::new_spanned(&ast.ident, "only structs with named fields can be derived"))
+    };
+}
+
+// #[derive(VertexLayout)] declares one attribute per field, #[location = N]
+// sets the attribute location, other fields follow the previous location
+#[proc_macro_derive(VertexLayout, attributes(location))]
+pub fn vertex_layout_derive(input: TokenStream) -> TokenStream {
+
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    let fields = match named_fields(&ast) {
+        Ok(fields) => fields,
+        Err(error) => { return error.to_compile_error().into(); }
+    };
+
+    let mut locations = Vec::new();
+    let mut next_location = 0u32;
+
+    for field in &fields.named {
+        let location = match field_location(field) {
+            Ok(Some(location)) => location,
+            Ok(None) => next_location,
+            Err(error) => { return error.to_compile_error().into(); }
+        };
+        if locations.contains(&location) {
+            return syn::Error::new_spanned(field, format!("location {} is used twice", location)).to_compile_error().into();
+        }
+        locations.push(location);
+        next_location = location + 1;
+    }
+
+    let name = &ast.ident;
+    let idents: Vec<_> = fields.named.iter().map(|field| field.ident.clone().unwrap()).collect();
+    let types: Vec<_> = fields.named.iter().map(|field| field.ty.clone()).collect();
+
+    let vertex_layout = quote!(::gamekit::graphics::vertex_layout);
+    let backend = quote!(::gamekit::graphics::backend);
+
+    let generated = quote! {
+
+        impl #vertex_layout::VertexLayout for #name {
+
+            fn attributes() -> Vec<#backend::VertexAttribute> {
+                return vec![#(
+                    #backend::VertexAttribute {
+                        location: #locations,
+                        components: <#types as #vertex_layout::VertexComponent>::COMPONENTS,
+                        data_type: <#types as #vertex_layout::VertexComponent>::DATA_TYPE,
+                        offset: ::std::mem::offset_of!(#name, #idents)
+                    }
+                ),*];
+            }
+
+        }
+
+        const _: () = {
+            #(#vertex_layout::check_component::<#types>();)*
+        };
+
+    };
+
+    return generated.into();
+}
+
+fn field_location(field: &Field) -> Result<Option<u32>, syn::Error> {
+
+    for attribute in &field.attrs {
+        if !attribute.path().is_ident("location") {
+            continue;
+        }
+
+        let value = &attribute.meta.require_name_value()?.value;
+        return match value {
+            Expr::Lit(ExprLit { lit: Lit::Int(location), .. }) => Ok(Some(location.base10_parse()?)),
+            _ => Err(syn::Error::new_spanned(value, "expected #[location = N]"))
+        };
+    }
+
+    return Ok(None);
+}